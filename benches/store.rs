@@ -0,0 +1,69 @@
+//! Compares `SimpleStore` and `IndexedStore` on a million tuples of the form
+//! `(i, kind, i / 1000)`, where every thousandth tuple is a "job" and all others are a "log".
+
+#[macro_use]
+extern crate rustupolis;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rustupolis::store::{IndexedStore, SimpleStore, Store};
+use rustupolis::tuple::{Tuple, E};
+use std::hint::black_box;
+
+const TUPLES: i32 = 1_000_000;
+
+fn tuple(i: i32) -> Tuple {
+    let kind = if i % 1000 == 0 { "job" } else { "log" };
+    tuple![E::I(i), E::str(kind), E::I(i / 1000)]
+}
+
+fn fill<S: Store>(mut store: S) -> S {
+    for i in 0..TUPLES {
+        store.out(tuple(i)).unwrap();
+    }
+    store
+}
+
+fn templates() -> Vec<(&'static str, Tuple)> {
+    vec![
+        ("defined", tuple(TUPLES / 2)),
+        ("first field", tuple![E::I(TUPLES / 2), E::Any, E::Any]),
+        (
+            "wildcard first",
+            tuple![E::Any, E::str("job"), E::I(TUPLES / 1000 - 1)],
+        ),
+        ("no match", tuple![E::Any, E::str("task"), E::Any]),
+    ]
+}
+
+fn bench_store<S: Store>(c: &mut Criterion, name: &str, store: &mut S) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for (label, template) in templates() {
+        group.bench_with_input(BenchmarkId::new("rdp", label), &template, |b, t| {
+            b.iter(|| store.rdp(black_box(t)).unwrap());
+        });
+    }
+    let jobs = tuple![E::Any, E::str("job"), E::Any];
+    group.bench_function("rd_all jobs", |b| {
+        b.iter(|| store.rd_all(black_box(&jobs)).unwrap());
+    });
+    let job = tuple![E::Any, E::str("job"), E::I(0)];
+    group.bench_function("inp and out", |b| {
+        b.iter(|| {
+            let tup = store.inp(black_box(&job)).unwrap().unwrap();
+            store.out(tup).unwrap();
+        });
+    });
+    group.finish();
+}
+
+fn simple_store(c: &mut Criterion) {
+    bench_store(c, "SimpleStore", &mut fill(SimpleStore::new()));
+}
+
+fn indexed_store(c: &mut Criterion) {
+    bench_store(c, "IndexedStore", &mut fill(IndexedStore::new()));
+}
+
+criterion_group!(benches, simple_store, indexed_store);
+criterion_main!(benches);
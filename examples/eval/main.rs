@@ -0,0 +1,43 @@
+//! This example demonstrates a master/worker pipeline built with `eval`. The master hands out
+//! active tuples whose fields are computed on the worker pool, then collects the results.
+
+#[macro_use]
+extern crate rustupolis;
+
+use futures::executor;
+
+use rustupolis::eval::Field;
+use rustupolis::space::SharedSpace;
+use rustupolis::store::SimpleStore;
+use rustupolis::tuple::E;
+
+fn collatz_steps(mut n: i32) -> i32 {
+    let mut steps = 0;
+    while n != 1 {
+        n = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+        steps += 1;
+    }
+    steps
+}
+
+fn main() {
+    println!("rustupolis - eval example");
+
+    let t_space = SharedSpace::new(SimpleStore::new());
+
+    // every worker puts a tuple ("steps", n, collatz_steps(n)) into the space, the evaluation
+    // keeps running in the background without awaiting it
+    for n in 1..=10 {
+        drop(t_space.eval(vec![
+            Field::from(E::str("steps")),
+            Field::from(E::I(n)),
+            Field::compute(move || E::I(collatz_steps(n))),
+        ]));
+    }
+
+    // the master waits for all results
+    for n in 1..=10 {
+        let result = executor::block_on(t_space.tuple_in(tuple![E::str("steps"), E::I(n), E::Any]));
+        println!("{:?}", result);
+    }
+}
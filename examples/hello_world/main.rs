@@ -30,7 +30,9 @@ fn main() {
 
     // look for a tuple that contains the string 'Hello"
     print!("inp(Any, Any) -> ");
-    let tup4 = t_store.inp(&tuple![E::S("Hello".to_string()), E::Any]);
+    let tup4 = t_store
+        .inp(&tuple![E::S("Hello".to_string()), E::Any])
+        .unwrap();
 
     match tup4 {
         Some(x) => {
@@ -7,21 +7,22 @@ extern crate rand_isaac;
 #[macro_use]
 extern crate rustupolis;
 
-use std::sync::{Arc, Mutex};
 use std::thread;
 
+use futures::executor;
 use rand::{RngExt, SeedableRng};
 use rand_isaac::isaac64::Isaac64Rng;
 
-use rustupolis::store::{InsertUndefinedTuple, SimpleStore, Store};
+use rustupolis::error::Error;
+use rustupolis::space::SharedSpace;
+use rustupolis::store::SimpleStore;
 use rustupolis::tuple::E;
 
-fn put_and_read(
+fn put(
     rng: &mut rand_isaac::isaac64::Isaac64Rng,
     id: &str,
-    t_store: std::sync::Arc<std::sync::Mutex<rustupolis::store::SimpleStore>>,
-) -> std::result::Result<(), InsertUndefinedTuple> {
-    let mut t_store = t_store.lock().unwrap();
+    t_space: &SharedSpace<SimpleStore>,
+) -> std::result::Result<(), Error> {
     for _i in 0..5 {
         println!("{0} pushing tuple", id);
         let mut strg = "tuple from ".to_string();
@@ -35,16 +36,18 @@ fn put_and_read(
             E::S("more content...".to_string()),
         ];
         println!("{:?}", tup);
-        t_store.out(tup)?;
+        executor::block_on(t_space.tuple_out(tup))?;
     }
+    Ok(())
+}
 
+fn take(id: &str, t_space: &SharedSpace<SimpleStore>) {
     for _i in 0..5 {
-        println!("reading tuple");
-        let tup = t_store.rdp(&tuple![E::Any, E::Any, E::Any, E::Any]);
-        println!("{:?}", tup);
+        println!("{0} waiting for a tuple", id);
+        // blocks until one of the other threads pushes a matching tuple
+        let tup = executor::block_on(t_space.tuple_in(tuple![E::Any, E::Any, E::Any, E::Any]));
+        println!("{0} took {1:?}", id, tup);
     }
-
-    Ok(())
 }
 
 fn main() {
@@ -55,24 +58,23 @@ fn main() {
     let mut rng1 = Isaac64Rng::from_seed(seed);
     let mut rng2 = Isaac64Rng::from_seed(seed);
 
-    // rng.reseed(seed);
-
     println!("rustupolis - multi threaded example");
 
-    let t_store = Arc::new(Mutex::new(SimpleStore::new()));
-    let ts1 = t_store.clone();
-    let handle_a = thread::spawn(move || {
-        put_and_read(&mut rng1, "a", ts1).unwrap();
-    });
+    let t_space = SharedSpace::new(SimpleStore::new());
 
-    let ts2 = t_store;
-    let handle_b = thread::spawn(move || {
-        put_and_read(&mut rng2, "b", ts2).unwrap();
-    });
+    // start the consumers first, they wait until the producers catch up
+    let ts_c = t_space.clone();
+    let handle_c = thread::spawn(move || take("c", &ts_c));
+    let ts_d = t_space.clone();
+    let handle_d = thread::spawn(move || take("d", &ts_d));
 
-    let res_a = handle_a.join();
-    let res_b = handle_b.join();
+    let ts_a = t_space.clone();
+    let handle_a = thread::spawn(move || put(&mut rng1, "a", &ts_a));
+    let ts_b = t_space;
+    let handle_b = thread::spawn(move || put(&mut rng2, "b", &ts_b));
 
-    println!("{:?}", res_a);
-    println!("{:?}", res_b);
+    println!("{:?}", handle_a.join());
+    println!("{:?}", handle_b.join());
+    println!("{:?}", handle_c.join());
+    println!("{:?}", handle_d.join());
 }
@@ -7,20 +7,18 @@ extern crate rand_isaac;
 #[macro_use]
 extern crate rustupolis;
 
-use rand::{Rng, SeedableRng};
+use rand::{RngExt, SeedableRng};
 use rand_isaac::isaac64::Isaac64Rng;
 
-use rustupolis::store::{InsertUndefinedTuple, SimpleStore, Store};
+use rustupolis::error::Error;
+use rustupolis::store::{SimpleStore, Store};
 use rustupolis::tuple::E;
 
-fn put_and_read(
-    rng: &mut Isaac64Rng,
-    t_store: &mut SimpleStore,
-) -> std::result::Result<(), InsertUndefinedTuple> {
+fn put_and_read(rng: &mut Isaac64Rng, t_store: &mut SimpleStore) -> std::result::Result<(), Error> {
     for _i in 0..5 {
         println!("pushing tuple");
-        let int = rng.gen::<i32>();
-        let dbl = rng.gen::<f64>();
+        let int = rng.random::<i32>();
+        let dbl = rng.random::<f64>();
         let tup = tuple![
             E::S("tuple".to_string()),
             E::I(int),
@@ -33,7 +31,7 @@ fn put_and_read(
 
     for _i in 0..5 {
         println!("reading tuple");
-        let tup = t_store.rdp(&tuple![E::Any, E::Any, E::Any, E::Any]);
+        let tup = t_store.rdp(&tuple![E::Any, E::Any, E::Any, E::Any])?;
         println!("{:?}", tup);
     }
 
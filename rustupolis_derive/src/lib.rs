@@ -0,0 +1,127 @@
+//! # Rustupolis Derive
+//!
+//! Derive macros that map structs to and from rustupolis tuples. The fields of a struct become
+//! the elements of a tuple, in the order in which they are declared.
+
+#![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Index};
+
+/// Derives `From<T> for Tuple` and `From<T> for E`, the latter nesting the struct as a tuple.
+/// Every field must implement `Into<E>`.
+#[proc_macro_derive(IntoTuple)]
+pub fn derive_into_tuple(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match struct_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let elements = fields.iter().map(|field| quote!(value.#field.into()));
+    quote! {
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::rustupolis::tuple::Tuple
+        #where_clause
+        {
+            fn from(value: #name #ty_generics) -> ::rustupolis::tuple::Tuple {
+                ::rustupolis::tuple::Tuple::from_vec(::std::vec![#(#elements),*])
+            }
+        }
+
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::rustupolis::tuple::E
+        #where_clause
+        {
+            fn from(value: #name #ty_generics) -> ::rustupolis::tuple::E {
+                ::rustupolis::tuple::E::T(::rustupolis::tuple::Tuple::from(value))
+            }
+        }
+    }
+    .into()
+}
+
+/// Derives `TryFrom<Tuple> for T` and `TryFrom<E> for T`, the latter expecting a nested tuple
+/// and wrapping its errors in `FromTupleError::Nested`.
+///
+/// Every field must implement `TryFrom<E, Error = FromTupleError>`.
+#[proc_macro_derive(FromTuple)]
+pub fn derive_from_tuple(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match struct_fields(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let arity = fields.len();
+    let vars = (0..arity)
+        .map(|i| format_ident!("e{i}"))
+        .collect::<Vec<_>>();
+    let values = vars
+        .iter()
+        .enumerate()
+        .map(|(i, var)| quote!(::rustupolis::convert::field(#var, #i)?));
+    let construct = match &input.data {
+        Data::Struct(data) if matches!(data.fields, Fields::Named(_)) => {
+            quote!(#name { #(#fields: #values),* })
+        }
+        Data::Struct(data) if matches!(data.fields, Fields::Unit) => quote!(#name),
+        _ => quote!(#name(#(#values),*)),
+    };
+    quote! {
+        impl #impl_generics ::std::convert::TryFrom<::rustupolis::tuple::Tuple> for #name #ty_generics
+        #where_clause
+        {
+            type Error = ::rustupolis::convert::FromTupleError;
+
+            fn try_from(
+                tup: ::rustupolis::tuple::Tuple,
+            ) -> ::std::result::Result<Self, ::rustupolis::convert::FromTupleError> {
+                let [#(#vars),*] = ::rustupolis::convert::elements::<#arity>(tup)?;
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+
+        impl #impl_generics ::std::convert::TryFrom<::rustupolis::tuple::E> for #name #ty_generics
+        #where_clause
+        {
+            type Error = ::rustupolis::convert::FromTupleError;
+
+            fn try_from(
+                e: ::rustupolis::tuple::E,
+            ) -> ::std::result::Result<Self, ::rustupolis::convert::FromTupleError> {
+                let tup: ::rustupolis::tuple::Tuple = ::std::convert::TryFrom::try_from(e)?;
+                ::std::convert::TryFrom::try_from(tup)
+                    .map_err(::rustupolis::convert::FromTupleError::nested)
+            }
+        }
+    }
+    .into()
+}
+
+/// Returns the names of the fields of a struct, or their indices for a tuple struct.
+fn struct_fields(input: &DeriveInput) -> Result<Vec<TokenStream2>, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "tuples can only be derived for structs",
+        ));
+    };
+    Ok(data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            field.ident.as_ref().map_or_else(
+                || {
+                    let index = Index::from(i);
+                    quote!(#index)
+                },
+                |ident| quote!(#ident),
+            )
+        })
+        .collect())
+}
@@ -22,14 +22,14 @@
 
 extern crate rustupolis;
 
+use std::fs::File;
 use std::io;
 use std::io::Write;
 
 use futures::executor;
-use rustupolis::lexing::Lexer;
+use rustupolis::lexing::{parse_tuples, TupleReader};
 use rustupolis::space::Space;
 use rustupolis::store::SimpleStore;
-use rustupolis::tuple::Tuple;
 
 fn main() {
     println!("Rustupolis CLI");
@@ -105,6 +105,7 @@ impl Cli {
             Some(&"close") => self.cmd_close(),
             Some(&"detach") => self.cmd_detach(),
             Some(&"out") => self.cmd_tuple_out(&tokens[1..]),
+            Some(&"load") => self.cmd_load(&tokens[1..]),
             Some(&"read" | &"rd" | &"take" | &"in") => self.cmd_tuple_read(&tokens[1..]),
             _ => {
                 println!("unknown command");
@@ -151,7 +152,13 @@ impl Cli {
             },
             |space| {
                 let param_list = parameters.join(" ");
-                let tuples: Vec<Tuple> = Lexer::new(&param_list).collect();
+                let tuples = match parse_tuples(&param_list) {
+                    Ok(tuples) => tuples,
+                    Err(e) => {
+                        eprintln!("Cannot parse tuples! {e}");
+                        return;
+                    }
+                };
                 for t in tuples {
                     if !t.is_empty() {
                         if t.is_defined() {
@@ -172,6 +179,41 @@ impl Cli {
         RequiredAction::NONE
     }
 
+    /// Push all tuples of a file into the tuple space, reading the file as it goes.
+    fn cmd_load(&mut self, parameters: &[&str]) -> RequiredAction {
+        self.tuplespace.as_mut().map_or_else(
+            || {
+                println!("Cannot load tuples into space! There is no tuple space initialised");
+            },
+            |space| {
+                let path = parameters.join(" ");
+                let file = match File::open(&path) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        eprintln!("Cannot open {path}! {e}");
+                        return;
+                    }
+                };
+                let mut loaded = 0;
+                for result in TupleReader::new(file) {
+                    match result {
+                        Ok(t) if t.is_defined() => {
+                            if let Err(e) = executor::block_on(space.tuple_out(t)) {
+                                eprintln!("Cannot push tuple into space! Encountered error {e:?}");
+                            } else {
+                                loaded += 1;
+                            }
+                        }
+                        Ok(t) => eprintln!("Cannot push tuple {t} into space! It is ill-defined."),
+                        Err(e) => eprintln!("Cannot read tuple from {path}! {e}"),
+                    }
+                }
+                println!("loaded {loaded} tuple(s) from {path} into tuple space");
+            },
+        );
+        RequiredAction::NONE
+    }
+
     fn cmd_tuple_read(&mut self, parameters: &[&str]) -> RequiredAction {
         self.tuplespace.as_mut().map_or_else(
             || {
@@ -179,16 +221,20 @@ impl Cli {
             },
             |space| {
                 let param_list = parameters.join(" ");
-                let tuples: Vec<Tuple> = Lexer::new(&param_list).collect();
+                let tuples = match parse_tuples(&param_list) {
+                    Ok(tuples) => tuples,
+                    Err(e) => {
+                        eprintln!("Cannot parse tuples! {e}");
+                        return;
+                    }
+                };
                 for rd_tup in tuples {
                     if !rd_tup.is_empty() {
                         println!("reading tuple matching {rd_tup} from space");
-                        if let Some(match_tup) = executor::block_on(space.tuple_rd(rd_tup)) {
-                            if match_tup.is_empty() {
-                                eprintln!("No matching tuple could be found.");
-                            } else {
-                                println!("found match: {match_tup}");
-                            }
+                        match space.try_rd(&rd_tup) {
+                            Ok(Some(match_tup)) => println!("found match: {match_tup}"),
+                            Ok(None) => eprintln!("No matching tuple could be found."),
+                            Err(e) => eprintln!("Cannot search the space! {e}"),
                         }
                     }
                 }
@@ -205,16 +251,20 @@ impl Cli {
             },
             |space| {
                 let param_list = parameters.join(" ");
-                let tuples: Vec<Tuple> = Lexer::new(&param_list).collect();
+                let tuples = match parse_tuples(&param_list) {
+                    Ok(tuples) => tuples,
+                    Err(e) => {
+                        eprintln!("Cannot parse tuples! {e}");
+                        return;
+                    }
+                };
                 for rd_tup in tuples {
                     if !rd_tup.is_empty() {
                         println!("pulling in tuple matching {rd_tup} from space");
-                        if let Some(match_tup) = executor::block_on(space.tuple_in(rd_tup)) {
-                            if match_tup.is_empty() {
-                                eprintln!("No matching tuple could be found.");
-                            } else {
-                                println!("found match: {match_tup}");
-                            }
+                        match space.try_in(&rd_tup) {
+                            Ok(Some(match_tup)) => println!("found match: {match_tup}"),
+                            Ok(None) => eprintln!("No matching tuple could be found."),
+                            Err(e) => eprintln!("Cannot search the space! {e}"),
                         }
                     }
                 }
@@ -1,16 +1,15 @@
-use rustupolis::space::Space;
+use rustupolis::space::SharedSpace;
 use rustupolis::store::SimpleStore;
-use std::sync::{Arc, Mutex};
 
 pub struct Client {
-    tuple_space:      Arc<Mutex<Space<SimpleStore>>>,
+    tuple_space:      SharedSpace<SimpleStore>,
     tuple_space_name: String,
     attributes:       Vec<String>,
 }
 
 impl Client {
     pub fn new(
-        tuple_space: Arc<Mutex<Space<SimpleStore>>>,
+        tuple_space: SharedSpace<SimpleStore>,
         attributes: Vec<String>,
         tuple_space_name: &str,
     ) -> Client {
@@ -22,7 +21,7 @@ impl Client {
         }
     }
 
-    pub fn tuple_space(&self) -> &Arc<Mutex<Space<SimpleStore>>> {
+    pub fn tuple_space(&self) -> &SharedSpace<SimpleStore> {
         &self.tuple_space
     }
 
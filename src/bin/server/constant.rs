@@ -5,6 +5,7 @@ pub const OUT: &str = "out";
 pub const IN: &str = "in";
 pub const READ: &str = "read";
 pub const ATTACH: &str = "attach";
+pub const TTL: &str = "ttl";
 pub const ADMIN_ATTRIBUTE: &str = "\"admin\"";
 pub const TUPLE_SPACE_ATTACHED: &str = "Tuple space attached";
 pub const TUPLE_SPACE_NOT_FOUND: &str = "ERROR - Tuple space not found";
@@ -13,6 +14,8 @@ pub const NO_TUPLE_SPACE_ATTACHED: &str = "ERROR - No tuple space attached";
 pub const OK: &str = "Successful request";
 pub const NO_PERMISSION: &str = "ERROR - No permission";
 pub const NO_MATCHING_TUPLE_FOUND: &str = "ERROR - No matching tuple could be found.";
+pub const INVALID_TTL: &str = "ERROR - The time to live must be a number of seconds";
+pub const INVALID_TUPLE: &str = "ERROR - The tuple could not be parsed";
 pub const TUPLE_IS_EMPTY: &str = "ERROR - The tuple is empty";
 pub const REQUEST_DOESNT_EXIST: &str = "ERROR - The request doesn't exist";
 pub const EMPTY_REQUEST: &str = "ERROR - The request is empty";
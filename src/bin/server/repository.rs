@@ -1,20 +1,24 @@
 use crate::client::Client;
 use crate::constant::{
-    ADMIN_ATTRIBUTE, ATTACH, CREATE, DELETE, EMPTY_REQUEST, IN, NO_MATCHING_TUPLE_FOUND,
-    NO_PERMISSION, NO_TUPLE_SPACE_ATTACHED, OUT, PERMISSION, READ, REQUEST_DOESNT_EXIST,
-    TUPLE_IS_EMPTY, TUPLE_SPACE_NOT_FOUND,
+    ADMIN_ATTRIBUTE, ATTACH, CREATE, DELETE, EMPTY_REQUEST, IN, INVALID_TTL, INVALID_TUPLE,
+    NO_MATCHING_TUPLE_FOUND, NO_PERMISSION, NO_TUPLE_SPACE_ATTACHED, OUT, PERMISSION, READ,
+    REQUEST_DOESNT_EXIST, TTL, TUPLE_IS_EMPTY, TUPLE_SPACE_NOT_FOUND,
 };
 use crate::repository::RequestResponse::{DataResponse, NoResponse, OkResponse, SpaceResponse};
 use futures::executor;
-use rustupolis::lexing::Lexer;
-use rustupolis::space::Space;
+use rustupolis::lexing::parse_tuples;
+use rustupolis::space::SharedSpace;
 use rustupolis::store::SimpleStore;
 use rustupolis::tuple;
 use rustupolis::tuple::{Tuple, E};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-type MutexedStore = Arc<Mutex<Space<SimpleStore>>>;
+type MutexedStore = SharedSpace<SimpleStore>;
+
+/// How often expired tuples are reclaimed from the tuple spaces.
+const PURGE_INTERVAL: Duration = Duration::from_secs(1);
 
 /// A repository of tuple spaces which a server has access to.
 pub struct Repository {
@@ -37,7 +41,7 @@ impl Default for Repository {
 
 impl Repository {
     pub fn new() -> Repository {
-        let permission = Arc::new(Mutex::new(Space::new(SimpleStore::new())));
+        let permission = SharedSpace::new(SimpleStore::new());
         let new_repository = Repository {
             tuple_spaces:           Arc::new(RwLock::new(HashMap::with_capacity(128))),
             permission_tuple_space: permission.clone(),
@@ -49,12 +53,10 @@ impl Repository {
             log::error!("Unable to acquire write access to tuple space repository, lock poisoned!");
         }
 
-        let mut permission_tuple_space = new_repository.permission_tuple_space.lock().unwrap();
-        let result = executor::block_on(permission_tuple_space.tuple_out(tuple!(
+        let result = executor::block_on(new_repository.permission_tuple_space.tuple_out(tuple!(
             E::str(CREATE),
             E::T(tuple!(E::str(ADMIN_ATTRIBUTE)))
         )));
-        drop(permission_tuple_space);
         new_repository.add_permission_list(vec![String::from(ADMIN_ATTRIBUTE)], PERMISSION);
         match result {
             Ok(_) => new_repository,
@@ -65,10 +67,9 @@ impl Repository {
     }
 
     pub fn add_tuple_space(&self, name: String) {
-        self.tuple_spaces
-            .write()
-            .unwrap()
-            .insert(name, Arc::new(Mutex::new(Space::new(SimpleStore::new()))));
+        let space = SharedSpace::new(SimpleStore::new());
+        space.purge_expired_every(PURGE_INTERVAL);
+        self.tuple_spaces.write().unwrap().insert(name, space);
     }
 
     pub fn remove_tuple_space(&self, name: &str) {
@@ -81,35 +82,24 @@ impl Repository {
         attributes: &[String],
         tuple_space_name: Option<&str>,
     ) -> bool {
-        let mut permission_space = self.permission_tuple_space.lock().unwrap();
+        let permission_space = &self.permission_tuple_space;
         return match action {
-            CREATE => {
-                match executor::block_on(permission_space.tuple_rd(tuple!(E::str(action), E::Any)))
-                {
-                    None => false,
-                    Some(tuple) => {
-                        if tuple.is_empty() {
-                            return false;
-                        }
-                        let rest = tuple.rest();
-                        Repository::compare_attributes(rest.first(), attributes)
-                    }
-                }
-            }
+            CREATE => match permission_space.try_rd(&tuple!(E::str(action), E::Any)) {
+                Ok(None) | Err(_) => false,
+                Ok(Some(tuple)) => tuple
+                    .get(1)
+                    .is_some_and(|e| Repository::compare_attributes(e, attributes)),
+            },
             _ => {
-                match executor::block_on(permission_space.tuple_rd(tuple!(
+                match permission_space.try_rd(&tuple!(
                     E::str(tuple_space_name.unwrap()),
                     E::str(action),
                     E::Any
-                ))) {
-                    None => false,
-                    Some(tuple) => {
-                        if tuple.is_empty() {
-                            return false;
-                        }
-                        let rest = tuple.rest().rest();
-                        Repository::compare_attributes(rest.first(), attributes)
-                    }
+                )) {
+                    Ok(None) | Err(_) => false,
+                    Ok(Some(tuple)) => tuple
+                        .get(2)
+                        .is_some_and(|e| Repository::compare_attributes(e, attributes)),
                 }
             }
         };
@@ -118,11 +108,8 @@ impl Repository {
     fn compare_attributes(attributes_permission: &E, attributes_client: &[String]) -> bool {
         if let E::T(tuple) = attributes_permission {
             let mut attributes_permission_list = Vec::with_capacity(156);
-            if let E::S(attribute) = tuple.first() {
-                attributes_permission_list.push(String::from(attribute));
-            }
-            while !tuple.rest().is_empty() {
-                if let E::S(attribute) = tuple.first() {
+            for element in tuple {
+                if let E::S(attribute) = element {
                     attributes_permission_list.push(String::from(attribute));
                 }
             }
@@ -156,7 +143,7 @@ impl Repository {
     }
 
     pub fn add_permission(&self, attribute: &str, action: &str, tuple_space_name: &str) {
-        let mut permission_space = self.permission_tuple_space.lock().unwrap();
+        let permission_space = &self.permission_tuple_space;
         match executor::block_on(permission_space.tuple_out(tuple!(
             E::str(tuple_space_name),
             E::str(action),
@@ -226,15 +213,36 @@ impl Repository {
                             client.attributes(),
                             Some(client.tuple_space_name()),
                         ) {
-                            let param_list = words[1..].join(" ");
-                            let tuple_list: Vec<Tuple> = Lexer::new(&param_list).collect();
+                            // out ttl <seconds> <tuples> lets the tuples expire
+                            let (ttl, params) = if words.get(1) == Some(&TTL) {
+                                // rejects negative, infinite and too large durations
+                                match words
+                                    .get(2)
+                                    .and_then(|secs| secs.parse::<f64>().ok())
+                                    .map(Duration::try_from_secs_f64)
+                                {
+                                    Some(Ok(ttl)) => (Some(ttl), &words[3..]),
+                                    _ => return NoResponse(String::from(INVALID_TTL)),
+                                }
+                            } else {
+                                (None, &words[1..])
+                            };
+                            let param_list = params.join(" ");
+                            let tuple_list = match parse_tuples(&param_list) {
+                                Ok(tuples) => tuples,
+                                Err(e) => return NoResponse(format!("{INVALID_TUPLE}: {e}")),
+                            };
                             for tuple in tuple_list {
                                 if !tuple.is_empty() {
                                     if tuple.is_defined() {
-                                        let mut space = client.tuple_space().lock().unwrap();
-                                        if let Err(error) =
-                                            executor::block_on(space.tuple_out(tuple))
-                                        {
+                                        let space = client.tuple_space();
+                                        let result = match ttl {
+                                            Some(ttl) => {
+                                                space.tuple_out_with_ttl(tuple, ttl).map(|_| ())
+                                            }
+                                            None => executor::block_on(space.tuple_out(tuple)),
+                                        };
+                                        if let Err(error) = result {
                                             eprintln!(
                                                 "Cannot push tuple into space! Encountered error {:?}",
                                                 error
@@ -266,17 +274,18 @@ impl Repository {
                             Some(client.tuple_space_name()),
                         ) {
                             let param_list = words[1..].join(" ");
-                            let mut tuples: Vec<Tuple> = Lexer::new(&param_list).collect();
+                            let mut tuples = match parse_tuples(&param_list) {
+                                Ok(tuples) => tuples,
+                                Err(e) => return NoResponse(format!("{INVALID_TUPLE}: {e}")),
+                            };
                             let mut response: RequestResponse = NoResponse(String::from(""));
                             let mut tuple_list: String = String::new();
                             let mut nb_tuples = 0;
                             for i in (0..tuples.len()).rev() {
                                 let rd_tup: Tuple = tuples.remove(i);
                                 if !rd_tup.is_empty() {
-                                    let mut space = client.tuple_space().lock().unwrap();
-                                    if let Some(match_tup) =
-                                        executor::block_on(space.tuple_rd(rd_tup))
-                                    {
+                                    let space = client.tuple_space();
+                                    if let Ok(Some(match_tup)) = space.try_rd(&rd_tup) {
                                         if match_tup.is_empty() {
                                             response =
                                                 NoResponse(String::from(NO_MATCHING_TUPLE_FOUND));
@@ -288,12 +297,15 @@ impl Repository {
                                                 tuple_list.push_str(", ");
                                             }
                                         }
+                                    } else {
+                                        response =
+                                            NoResponse(String::from(NO_MATCHING_TUPLE_FOUND));
                                     }
                                 } else {
                                     response = NoResponse(String::from(TUPLE_IS_EMPTY));
                                 }
                             }
-                            if tuple_list.eq(&String::from("(")) {
+                            if tuple_list.is_empty() {
                                 response
                             } else if nb_tuples > 1 {
                                 DataResponse("(".to_owned() + &tuple_list + ")")
@@ -315,18 +327,19 @@ impl Repository {
                             Some(client.tuple_space_name()),
                         ) {
                             let param_list = words[1..].join(" ");
-                            let mut tuples: Vec<Tuple> = Lexer::new(&param_list).collect();
+                            let mut tuples = match parse_tuples(&param_list) {
+                                Ok(tuples) => tuples,
+                                Err(e) => return NoResponse(format!("{INVALID_TUPLE}: {e}")),
+                            };
                             let mut response: RequestResponse = NoResponse(String::from(""));
                             let mut tuple_list: String = String::new();
                             let mut nb_tuples = 0;
                             for i in (0..tuples.len()).rev() {
                                 let rd_tup: Tuple = tuples.remove(i);
                                 if !rd_tup.is_empty() {
-                                    let mut space = client.tuple_space().lock().unwrap();
+                                    let space = client.tuple_space();
                                     println!("pulling in tuple matching {} from space", rd_tup);
-                                    if let Some(match_tup) =
-                                        executor::block_on(space.tuple_in(rd_tup))
-                                    {
+                                    if let Ok(Some(match_tup)) = space.try_in(&rd_tup) {
                                         if match_tup.is_empty() {
                                             response =
                                                 NoResponse(String::from(NO_MATCHING_TUPLE_FOUND));
@@ -337,12 +350,15 @@ impl Repository {
                                                 tuple_list.push_str(", ");
                                             }
                                         }
+                                    } else {
+                                        response =
+                                            NoResponse(String::from(NO_MATCHING_TUPLE_FOUND));
                                     }
                                 } else {
                                     response = NoResponse(String::from(TUPLE_IS_EMPTY));
                                 }
                             }
-                            if tuple_list.eq(&String::from("(")) {
+                            if tuple_list.is_empty() {
                                 response
                             } else if nb_tuples > 1 {
                                 DataResponse("(".to_owned() + &tuple_list + ")")
@@ -0,0 +1,200 @@
+//! Module Constraint
+//!
+//! Constraints are template elements that match a whole set of values instead of a single one,
+//! like all integers greater than ten or all strings with a certain prefix.
+//!
+//! Within a template a constraint sorts right before the lowest value it can match, so that
+//! stores can narrow their range scans down to the matching values.
+
+use std::cmp::Ordering;
+use std::collections::Bound;
+use std::fmt::{Display, Formatter, Result};
+
+use regex::Regex;
+
+use crate::tuple::{Type, E};
+
+/// A constraint on the value of a tuple element.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Constraint {
+    /// Values of the same type as the bounds that lie within the bounds.
+    Range(Bound<E>, Bound<E>),
+    /// Strings starting with the prefix.
+    Prefix(String),
+    /// Strings matching the regular expression.
+    Regex(#[cfg_attr(feature = "serde", serde(with = "serde_regex"))] Regex),
+    /// Values equal to one of the elements.
+    OneOf(Vec<E>),
+}
+
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Constraint) -> bool {
+        match (self, other) {
+            (Constraint::Range(a_lo, a_hi), Constraint::Range(b_lo, b_hi)) => {
+                a_lo == b_lo && a_hi == b_hi
+            }
+            (Constraint::Prefix(a), Constraint::Prefix(b)) => a == b,
+            (Constraint::Regex(a), Constraint::Regex(b)) => a.as_str() == b.as_str(),
+            (Constraint::OneOf(a), Constraint::OneOf(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Constraint::Range(lo, hi) => {
+                match lo {
+                    Bound::Included(lo) if matches!(hi, Bound::Unbounded) => write!(f, ">={lo}")?,
+                    Bound::Included(lo) => write!(f, "{lo}")?,
+                    Bound::Excluded(lo) => write!(f, ">{lo}")?,
+                    Bound::Unbounded => match hi {
+                        Bound::Included(hi) => return write!(f, "<={hi}"),
+                        Bound::Excluded(hi) => return write!(f, "<{hi}"),
+                        Bound::Unbounded => return write!(f, ".."),
+                    },
+                }
+                match hi {
+                    Bound::Included(hi) => write!(f, "..={hi}"),
+                    Bound::Excluded(hi) => write!(f, "..{hi}"),
+                    Bound::Unbounded => Ok(()),
+                }
+            }
+            Constraint::Prefix(p) => write!(f, "^\"{}\"", p.escape_debug()),
+            Constraint::Regex(r) => write!(f, "~\"{}\"", r.as_str().escape_debug()),
+            Constraint::OneOf(es) => write!(
+                f,
+                "in [{}]",
+                es.iter()
+                    .map(std::string::ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
+impl Constraint {
+    /// Returns true if the value satisfies the constraint. Wildcards never do.
+    #[must_use]
+    pub fn matches(&self, e: &E) -> bool {
+        if !e.is_defined() {
+            return false;
+        }
+        match self {
+            Constraint::Range(lo, hi) => {
+                let same_type = |b: &E| b.type_of() == e.type_of();
+                let above = match lo {
+                    Bound::Included(b) => same_type(b) && e >= b,
+                    Bound::Excluded(b) => same_type(b) && e > b,
+                    Bound::Unbounded => true,
+                };
+                let below = match hi {
+                    Bound::Included(b) => same_type(b) && e <= b,
+                    Bound::Excluded(b) => same_type(b) && e < b,
+                    Bound::Unbounded => true,
+                };
+                above && below
+            }
+            Constraint::Prefix(p) => matches!(e, E::S(s) if s.starts_with(p.as_str())),
+            Constraint::Regex(r) => matches!(e, E::S(s) if r.is_match(s)),
+            Constraint::OneOf(es) => es.iter().any(|x| x.matches(e)),
+        }
+    }
+
+    /// The type of all values satisfying the constraint, if there is only one.
+    const fn type_of(&self) -> Option<Type> {
+        match self {
+            Constraint::Range(Bound::Included(b) | Bound::Excluded(b), _)
+            | Constraint::Range(_, Bound::Included(b) | Bound::Excluded(b)) => b.type_of(),
+            Constraint::Prefix(_) | Constraint::Regex(_) => Some(Type::Str),
+            Constraint::Range(Bound::Unbounded, Bound::Unbounded) | Constraint::OneOf(_) => None,
+        }
+    }
+
+    /// The position of the constraint among other elements: the element it sorts next to and
+    /// whether it sorts right before or right after it.
+    pub(crate) fn position(&self) -> (E, Ordering) {
+        match self {
+            Constraint::Range(Bound::Included(b), _) => (b.clone(), Ordering::Less),
+            Constraint::Range(Bound::Excluded(b), _) => (b.clone(), Ordering::Greater),
+            Constraint::Prefix(p) => (E::S(p.clone()), Ordering::Less),
+            Constraint::OneOf(es) => es.iter().min().map_or((E::None, Ordering::Less), |min| {
+                (min.clone(), Ordering::Less)
+            }),
+            Constraint::Range(Bound::Unbounded, _) | Constraint::Regex(_) => {
+                self.type_of().map_or((E::Any, Ordering::Greater), |t| {
+                    (E::AnyOf(t), Ordering::Greater)
+                })
+            }
+        }
+    }
+
+    /// An element that sorts after all values satisfying the constraint.
+    pub(crate) fn terminator(&self) -> E {
+        let after = |e: &E| {
+            E::Where(Box::new(Constraint::Range(
+                Bound::Excluded(e.clone()),
+                Bound::Unbounded,
+            )))
+        };
+        let before = |e: E| {
+            E::Where(Box::new(Constraint::Range(
+                Bound::Included(e),
+                Bound::Unbounded,
+            )))
+        };
+        match self {
+            Constraint::Range(_, Bound::Included(b)) => after(b),
+            Constraint::Range(_, Bound::Excluded(b)) => before(b.clone()),
+            Constraint::Prefix(p) => {
+                prefix_end(p).map_or_else(|| self.end_of_type(), |end| before(E::S(end)))
+            }
+            Constraint::OneOf(es) => es.iter().max().map_or(E::None, after),
+            Constraint::Range(_, Bound::Unbounded) | Constraint::Regex(_) => self.end_of_type(),
+        }
+    }
+
+    /// An element that sorts after all values of the constraint's type.
+    fn end_of_type(&self) -> E {
+        self.type_of()
+            .and_then(Type::next)
+            .map_or(E::None, E::AnyOf)
+    }
+}
+
+/// Returns the smallest string that is greater than all strings starting with the prefix.
+fn prefix_end(prefix: &str) -> Option<String> {
+    let mut chars = prefix.chars().collect::<Vec<char>>();
+    while let Some(c) = chars.pop() {
+        let next = match c {
+            '\u{d7ff}' => Some('\u{e000}'),
+            c => char::from_u32(c as u32 + 1),
+        };
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+    None
+}
+
+/// Serializes regular expressions as their pattern.
+#[cfg(feature = "serde")]
+mod serde_regex {
+    use regex::Regex;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(d)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,185 @@
+//! Module Convert
+//!
+//! Conversions between Rust values and tuple elements, and between Rust tuples and `Tuple`.
+//! Structs can be converted with `#[derive(IntoTuple, FromTuple)]`.
+
+use std::fmt;
+
+use crate::tuple::{Tuple, Type, E};
+
+/// The error returned when a tuple or element does not have the expected shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromTupleError {
+    /// The tuple has a different number of elements than expected.
+    Arity { expected: usize, found: usize },
+    /// The element at the given position of the tuple has a different type than expected.
+    Type {
+        position: usize,
+        expected: Type,
+        found:    E,
+    },
+    /// The nested tuple at the given position of the tuple cannot be converted.
+    Nested {
+        position: usize,
+        error:    Box<FromTupleError>,
+    },
+}
+
+impl FromTupleError {
+    /// Places a type error or an error of a nested tuple at the given position of the tuple.
+    /// The positions within the nested tuple are kept.
+    #[must_use]
+    pub fn at(self, position: usize) -> FromTupleError {
+        match self {
+            FromTupleError::Type {
+                expected, found, ..
+            } => FromTupleError::Type {
+                position,
+                expected,
+                found,
+            },
+            FromTupleError::Nested { error, .. } => FromTupleError::Nested { position, error },
+            arity @ FromTupleError::Arity { .. } => arity,
+        }
+    }
+
+    /// Wraps the error of converting a nested tuple, to be placed with `at`.
+    #[must_use]
+    pub fn nested(self) -> FromTupleError {
+        FromTupleError::Nested {
+            position: 0,
+            error:    Box::new(self),
+        }
+    }
+}
+
+impl std::error::Error for FromTupleError {}
+
+impl fmt::Display for FromTupleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromTupleError::Arity { expected, found } => {
+                write!(f, "expected a tuple of {expected} elements, found {found}")
+            }
+            FromTupleError::Type {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {expected} at position {position}, found {found}"
+            ),
+            FromTupleError::Nested { position, error } => {
+                write!(f, "{error} in the tuple at position {position}")
+            }
+        }
+    }
+}
+
+/// Takes the elements out of a tuple that must have exactly `N` of them.
+///
+/// # Errors
+/// `FromTupleError::Arity` if the tuple has a different number of elements.
+pub fn elements<const N: usize>(tup: Tuple) -> Result<[E; N], FromTupleError> {
+    <[E; N]>::try_from(tup.into_vec()).map_err(|v| FromTupleError::Arity {
+        expected: N,
+        found:    v.len(),
+    })
+}
+
+/// Converts the element at the given position of a tuple.
+///
+/// # Errors
+/// `FromTupleError::Type` if the element has a different type.
+pub fn field<T>(e: E, position: usize) -> Result<T, FromTupleError>
+where
+    T: TryFrom<E, Error = FromTupleError>,
+{
+    T::try_from(e).map_err(|err| err.at(position))
+}
+
+macro_rules! impl_element {
+    ($t:ty, $variant:ident, $typ:ident) => {
+        impl From<$t> for E {
+            fn from(v: $t) -> E {
+                E::$variant(v)
+            }
+        }
+
+        impl TryFrom<E> for $t {
+            type Error = FromTupleError;
+
+            fn try_from(e: E) -> Result<$t, FromTupleError> {
+                match e {
+                    E::$variant(v) => Ok(v),
+                    found => Err(FromTupleError::Type {
+                        position: 0,
+                        expected: Type::$typ,
+                        found,
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_element!(i32, I, Int);
+impl_element!(i64, L, Long);
+impl_element!(u64, U, Unsigned);
+impl_element!(f64, D, Float);
+impl_element!(bool, B, Bool);
+impl_element!(String, S, Str);
+impl_element!(Vec<u8>, R, Bytes);
+impl_element!(Tuple, T, Tuple);
+
+impl From<&str> for E {
+    fn from(s: &str) -> E {
+        E::S(s.to_string())
+    }
+}
+
+impl From<&[u8]> for E {
+    fn from(r: &[u8]) -> E {
+        E::R(r.to_vec())
+    }
+}
+
+impl From<Vec<E>> for Tuple {
+    fn from(v: Vec<E>) -> Tuple {
+        Tuple::from_vec(v)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($n:literal; $($t:ident $v:ident $i:tt),+) => {
+        impl<$($t),+> From<($($t,)+)> for Tuple
+        where
+            $($t: Into<E>),+
+        {
+            fn from(($($v,)+): ($($t,)+)) -> Tuple {
+                Tuple::from_vec(vec![$($v.into()),+])
+            }
+        }
+
+        impl<$($t),+> TryFrom<Tuple> for ($($t,)+)
+        where
+            $($t: TryFrom<E, Error = FromTupleError>),+
+        {
+            type Error = FromTupleError;
+
+            fn try_from(tup: Tuple) -> Result<($($t,)+), FromTupleError> {
+                let [$($v),+] = elements::<$n>(tup)?;
+                Ok(($(field($v, $i)?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(1; A a 0);
+impl_tuple!(2; A a 0, B b 1);
+impl_tuple!(3; A a 0, B b 1, C c 2);
+impl_tuple!(4; A a 0, B b 1, C c 2, D d 3);
+impl_tuple!(5; A a 0, B b 1, C c 2, D d 3, F f 4);
+impl_tuple!(6; A a 0, B b 1, C c 2, D d 3, F f 4, G g 5);
+impl_tuple!(7; A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6);
+impl_tuple!(8; A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6, J j 7);
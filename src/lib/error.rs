@@ -1,4 +1,68 @@
-error_chain! {
-    errors {
+//! Module Error
+//!
+//! The error type shared by stores, spaces, the lexer and the wildcard tree.
+
+use std::{error, fmt, io};
+
+use crate::lexing::ParseError;
+
+/// The error returned by all fallible operations of the library.
+#[derive(Debug)]
+pub enum Error {
+    /// An undefined tuple, one with wildcards or constraints, was put into a space or store.
+    UndefinedTuple,
+    /// The input could not be parsed as a tuple.
+    Parse(ParseError),
+    /// The space was dropped while waiting for a matching tuple.
+    WaiterDisconnected,
+    /// No matching tuple arrived before the timeout.
+    TimedOut,
+    /// Reading or writing the storage behind a store failed.
+    Io(io::Error),
+    /// The store cannot hold any more tuples.
+    CapacityExceeded,
+    /// The operation is not permitted on the space.
+    PermissionDenied,
+    /// An active field of an evaluated tuple panicked.
+    FieldPanicked,
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UndefinedTuple => write!(f, "attempted to insert an undefined tuple"),
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::WaiterDisconnected => write!(
+                f,
+                "the space was dropped while waiting for a matching tuple"
+            ),
+            Error::TimedOut => write!(f, "timed out waiting for a matching tuple"),
+            Error::Io(e) => write!(f, "store I/O failed: {e}"),
+            Error::CapacityExceeded => write!(f, "the store is full"),
+            Error::PermissionDenied => write!(f, "permission denied"),
+            Error::FieldPanicked => write!(f, "computing an active field failed"),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
     }
 }
@@ -0,0 +1,84 @@
+//! Module Eval
+//!
+//! Active tuples for the Linda `eval` operation. The fields of an active tuple are computed
+//! concurrently on a worker pool. Once all of them are done the resulting passive tuple is put
+//! into the space.
+
+use futures::channel::oneshot;
+use futures::executor::ThreadPool;
+use futures::future::{self, BoxFuture};
+use futures::FutureExt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::OnceLock;
+
+use crate::error::Error;
+use crate::tuple::{Tuple, E};
+
+/// A field of an active tuple.
+pub enum Field {
+    /// A field whose value is already known.
+    Value(E),
+    /// A field whose value is still being computed.
+    Active(BoxFuture<'static, E>),
+}
+
+impl Field {
+    /// A field computed by running the closure on a worker thread.
+    pub fn compute<F>(f: F) -> Field
+    where
+        F: FnOnce() -> E + Send + 'static,
+    {
+        Field::Active(future::lazy(move |_| f()).boxed())
+    }
+
+    /// A field computed by driving the future on a worker thread.
+    pub fn future<F>(f: F) -> Field
+    where
+        F: Future<Output = E> + Send + 'static,
+    {
+        Field::Active(f.boxed())
+    }
+}
+
+impl From<E> for Field {
+    fn from(e: E) -> Field {
+        Field::Value(e)
+    }
+}
+
+/// The worker pool shared by all active tuples, started on first use.
+///
+/// # Panics
+/// If the worker threads cannot be spawned.
+pub(crate) fn pool() -> &'static ThreadPool {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        ThreadPool::builder()
+            .name_prefix("rustupolis-eval-")
+            .create()
+            .expect("unable to start the eval worker pool")
+    })
+}
+
+/// Spawns all active fields on the worker pool and returns a future resolving to the passive
+/// tuple once every field has been computed. A field that panics fails the whole tuple.
+pub(crate) fn evaluate(fields: Vec<Field>) -> impl Future<Output = Result<Tuple, Error>> {
+    let values = fields
+        .into_iter()
+        .map(|field| match field {
+            Field::Value(e) => future::ok(e).boxed(),
+            Field::Active(f) => {
+                let (tx, rx) = oneshot::channel();
+                pool().spawn_ok(AssertUnwindSafe(f).catch_unwind().map(|result| {
+                    if let Ok(e) = result {
+                        let _ = tx.send(e);
+                    }
+                }));
+                rx.map(|result| result.map_err(|_| Error::FieldPanicked))
+                    .boxed()
+            }
+        })
+        .collect::<Vec<_>>();
+    future::try_join_all(values).map(|result| result.map(Tuple::from_vec))
+}
@@ -1,19 +1,31 @@
 //! Module Lexing
 //!
 //! Parsing of strings into instances of Tuple
+//! The syntax is the one tuples are displayed in, so that displayed tuples can be read back.
 //! Heavily inspired by
 //!     <https://users.rust-lang.org/t/an-suggestions-improvements-for-my-lexer/6081>
 
-use crate::tuple::{Tuple, E};
-use std::{error, fmt, result};
+use crate::constraint::Constraint;
+use crate::error::Error;
+use crate::tuple::{Tuple, Type, E};
+use regex::Regex;
+use std::collections::{Bound, VecDeque};
+use std::{error, fmt, io, mem, result};
 
 #[derive(Debug)]
 enum TokenType<'a> {
-    Integer,
-    Float,
-    String,
+    Integer(i32),
+    Long(i64),
+    Unsigned(u64),
+    Float(f64),
+    Bool,
+    String(String),
+    Bytes(Vec<u8>),
     Tuple(Vec<Token<'a>>),
     Wildcard,
+    Formal(Type),
+    Constraint(Constraint),
+    Nil,
 }
 
 #[derive(Debug)]
@@ -24,39 +36,138 @@ struct Token<'a> {
 
 type Result<Token> = result::Result<Token, ParseError>;
 
-#[derive(Debug, Clone)]
-struct ParseError;
+/// How deeply tuples and range bounds may be nested within a tuple. Deeper input is rejected
+/// instead of overflowing the stack.
+pub const MAX_DEPTH: usize = 128;
+
+/// The kind of token the lexer expected where it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A tuple, starting with `(`.
+    Tuple,
+    /// An element of a tuple or set.
+    Element,
+    /// An integer or floating point number.
+    Number,
+    /// A value bounding a range.
+    Bound,
+    /// The name of a type, following `?`.
+    Type,
+    /// An escape sequence or an ASCII character in a byte string.
+    Escape,
+    /// A valid regular expression.
+    Regex,
+    /// The given character.
+    Char(char),
+    /// The end of the input.
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Tuple => write!(f, "a tuple"),
+            TokenKind::Element => write!(f, "an element"),
+            TokenKind::Number => write!(f, "a number"),
+            TokenKind::Bound => write!(f, "a range bound"),
+            TokenKind::Type => write!(f, "a type name"),
+            TokenKind::Escape => write!(f, "an escape sequence"),
+            TokenKind::Regex => write!(f, "a regular expression"),
+            TokenKind::Char(c) => write!(f, "`{c}`"),
+            TokenKind::End => write!(f, "the end of the input"),
+        }
+    }
+}
+
+/// The reason why input is not a valid list of tuples, returned wrapped in `Error::Parse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset:   usize,
+    expected: TokenKind,
+    message:  String,
+}
+
+impl ParseError {
+    /// The byte offset into the input at which parsing failed.
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The kind of token that was expected at the offset.
+    #[must_use]
+    pub const fn expected(&self) -> TokenKind {
+        self.expected
+    }
+
+    /// A description of what went wrong.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
 
 impl error::Error for ParseError {}
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "unable to parse token")
+        write!(
+            f,
+            "{} at byte {}, expected {}",
+            self.message, self.offset, self.expected
+        )
+    }
+}
+
+/// Parses a string holding exactly one tuple, like `(1, "foo", _)`.
+///
+/// # Errors
+/// `Error::Parse` if the input is not a single valid tuple.
+pub fn parse_tuple(input: &str) -> result::Result<Tuple, Error> {
+    Ok(parse_single(input)?)
+}
+
+/// Parses a string holding any number of tuples, separated by commas or whitespace.
+///
+/// # Errors
+/// `Error::Parse` for the first invalid tuple of the input.
+pub fn parse_tuples(input: &str) -> result::Result<Vec<Tuple>, Error> {
+    let mut lexer = Lexer::new(input);
+    std::iter::from_fn(|| lexer.next_tuple()).collect()
+}
+
+fn parse_single(input: &str) -> Result<Tuple> {
+    let mut lexer = Lexer::new(input);
+    let tup = lexer
+        .tuple()
+        .unwrap_or_else(|| Err(lexer.error(TokenKind::Tuple, "empty input")))?;
+    lexer.skip_separators();
+    if lexer.pos < input.len() {
+        return Err(lexer.error(TokenKind::End, "more than one tuple"));
     }
+    Ok(tup)
 }
 
+/// Iterates over the tuples of a string. Iteration stops at the first invalid tuple, use
+/// `next_tuple` or `parse_tuples` to find out what is wrong with it.
 pub struct Lexer<'a> {
-    buf: &'a str,
-    pos: usize,
+    buf:   &'a str,
+    pos:   usize,
+    /// The number of values that are being parsed within each other.
+    depth: usize,
 }
 
-impl<'a> Iterator for Lexer<'a> {
+impl Iterator for Lexer<'_> {
     type Item = Tuple;
 
     fn next(&mut self) -> Option<Tuple> {
-        let chars = self.buf.chars().collect::<Vec<char>>();
-
-        if self.pos >= chars.len() {
-            return None;
-        }
-        let next_token = &self.match_next(&chars);
-        next_token.as_ref().map_or(None, |token| {
-            if let E::T(tuple) = Self::from_token(token) {
-                Some(tuple)
-            } else {
-                Some(tuple![])
+        match self.next_tuple()? {
+            Ok(tup) => Some(tup),
+            Err(e) => {
+                warn!("{e}");
+                None
             }
-        })
+        }
     }
 }
 
@@ -64,51 +175,146 @@ impl<'a> Lexer<'a> {
     #[must_use]
     pub const fn new(buffer: &str) -> Lexer<'_> {
         Lexer {
-            buf: buffer,
-            pos: 0,
+            buf:   buffer,
+            pos:   0,
+            depth: 0,
+        }
+    }
+
+    /// Parses the next tuple of the input, or returns `None` at the end of the input.
+    /// The rest of the input is skipped after an error.
+    pub fn next_tuple(&mut self) -> Option<result::Result<Tuple, Error>> {
+        Some(self.tuple()?.map_err(Error::Parse))
+    }
+
+    fn tuple(&mut self) -> Option<Result<Tuple>> {
+        self.skip_separators();
+        let result = match self.peek()? {
+            '(' => self
+                .parse_items()
+                .map(|items| Tuple::from_vec(items.iter().map(Self::from_token).collect())),
+            c => Err(self.error(TokenKind::Tuple, format!("unexpected {c:?}"))),
+        };
+        if result.is_err() {
+            self.pos = self.buf.len();
+        }
+        Some(result)
+    }
+
+    fn error(&self, expected: TokenKind, message: impl Into<String>) -> ParseError {
+        self.error_at(self.pos, expected, message)
+    }
+
+    fn error_at(
+        &self,
+        offset: usize,
+        expected: TokenKind,
+        message: impl Into<String>,
+    ) -> ParseError {
+        ParseError {
+            offset: offset.min(self.buf.len()),
+            expected,
+            message: message.into(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.buf[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    fn skip_while<F: Fn(char) -> bool>(&mut self, pred: F) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+        &self.buf[start..self.pos]
+    }
+
+    fn skip_separators(&mut self) {
+        self.skip_while(|c| c == ',' || c.is_whitespace());
+    }
+
+    fn match_next(&mut self) -> Result<Token<'a>> {
+        self.skip_separators();
+        let start = self.pos;
+        let token = self.parse_value()?;
+        // a value followed by two dots is the lower bound of a range like 1..5
+        if !self.rest().starts_with("..") {
+            return Ok(token);
         }
+        let lo = Bound::Included(self.bound_from_token(start, &token)?);
+        let hi = self.parse_upper_bound()?;
+        Ok(self.constraint_token(start, Constraint::Range(lo, hi)))
     }
 
-    fn match_next(&mut self, chars: &[char]) -> Result<Token<'a>> {
-        match chars[self.pos] {
+    fn parse_value(&mut self) -> Result<Token<'a>> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(TokenKind::Element, "nested too deeply"));
+        }
+        self.depth += 1;
+        let token = self.parse_nested();
+        self.depth -= 1;
+        token
+    }
+
+    fn parse_nested(&mut self) -> Result<Token<'a>> {
+        match self.peek() {
             // parse numbers, which can be either negative or positive
-            '-' | '0'..='9' => self.parse_number(chars),
+            Some('-' | '0'..='9') => self.parse_number(),
+            // parse ranges like >10, <=5 or ..3
+            Some('>' | '<' | '.') => self.parse_range(),
+            // parse string prefixes like ^"foo" and regular expressions like ~"fo+"
+            Some('^' | '~') => self.parse_pattern(),
             // parse strings that are started and terminated by quote marks
-            '\"' => self.parse_string(chars),
+            Some('\"') => self.parse_string(),
             // use a special character for wildcards
-            '_' => Ok(self.parse_wildcard()),
+            Some('_') => Ok(self.parse_wildcard()),
+            // typed wildcards name the type they match, e.g. ?int
+            Some('?') => self.parse_formal(),
+            // parse booleans, special floats, nil and byte strings, which are prefixed with a b
+            Some('a'..='z') => self.parse_word(),
             // parse tuples which are surrounded by parentheses
-            '(' => self.parse_tuple(chars),
-            ',' | ' ' => {
-                self.pos += 1;
-                self.match_next(chars)
-            }
-            _ => {
-                println!("invalid symbol {}", chars[self.pos]);
-                self.pos += 1;
-                self.match_next(chars)
-            }
+            Some('(') => self.parse_tuple(),
+            Some(c) => Err(self.error(TokenKind::Element, format!("unexpected {c:?}"))),
+            None => Err(self.error(TokenKind::Element, "unexpected end of input")),
         }
     }
 
-    fn parse_number(&mut self, chars: &[char]) -> Result<Token<'a>> {
+    fn parse_number(&mut self) -> Result<Token<'a>> {
         let start = self.pos;
+        if self.rest().starts_with("-inf") {
+            self.pos += 4;
+            return Ok(Token {
+                typ: TokenType::Float(f64::NEG_INFINITY),
+                val: &self.buf[start..self.pos],
+            });
+        }
+        self.eat('-');
         let mut is_float = false;
-        while self.pos < chars.len() {
-            match chars[self.pos] {
-                '0'..='9' => self.pos += 1,
-                '-' => {
-                    if self.pos == start {
-                        self.pos += 1;
-                    } else {
-                        // only allow a minus at the end of a number
-                        break;
-                    }
-                }
-                '.' => {
-                    if is_float {
-                        return Err(ParseError);
-                    }
+        loop {
+            match self.peek() {
+                Some('0'..='9') => self.pos += 1,
+                // two dots start the upper bound of a range
+                Some('.') if self.rest().starts_with("..") => break,
+                Some('.') if !is_float => {
                     is_float = true;
                     self.pos += 1;
                 }
@@ -116,88 +322,391 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        let typ = if is_float {
-            TokenType::Float
-        } else {
-            TokenType::Integer
+        let end = self.pos;
+        let val = &self.buf[start..end];
+        // integers can carry a suffix for their width and signedness
+        let (typ, suffix_len) = match self.buf.get(end..end + 3) {
+            _ if is_float => (val.parse().ok().map(TokenType::Float), 0),
+            Some("i32") => (val.parse().ok().map(TokenType::Integer), 3),
+            Some("i64") => (val.parse().ok().map(TokenType::Long), 3),
+            Some("u64") => (val.parse().ok().map(TokenType::Unsigned), 3),
+            _ => (val.parse().ok().map(TokenType::Integer), 0),
         };
+        let Some(typ) = typ else {
+            return Err(self.error_at(start, TokenKind::Number, format!("invalid number {val:?}")));
+        };
+        self.pos += suffix_len;
         Ok(Token {
             typ,
             val: &self.buf[start..self.pos],
         })
     }
 
-    fn parse_string(&mut self, chars: &[char]) -> Result<Token<'a>> {
-        self.pos += 1;
+    fn parse_word(&mut self) -> Result<Token<'a>> {
+        if self.rest().starts_with("b\"") {
+            return self.parse_bytes();
+        }
         let start = self.pos;
-        while chars[self.pos] != '\"' {
-            self.pos += 1;
+        let val = self.skip_while(|c| c.is_ascii_alphanumeric());
+        match val {
+            "true" | "false" => Ok(Token {
+                typ: TokenType::Bool,
+                val,
+            }),
+            "inf" => Ok(Token {
+                typ: TokenType::Float(f64::INFINITY),
+                val,
+            }),
+            "nan" => Ok(Token {
+                typ: TokenType::Float(f64::NAN),
+                val,
+            }),
+            "nil" => Ok(Token {
+                typ: TokenType::Nil,
+                val,
+            }),
+            "in" => self.parse_set(start),
+            _ => Err(self.error_at(start, TokenKind::Element, format!("unknown word {val:?}"))),
+        }
+    }
 
-            if self.pos >= chars.len() {
-                eprintln!("error: incomplete string!");
-                return Err(ParseError);
+    /// Parses a byte string like `b"\x00abc"`, which holds ASCII characters and the escape
+    /// sequences `\t`, `\r`, `\n`, `\'`, `\"`, `\\` and `\xNN`.
+    fn parse_bytes(&mut self) -> Result<Token<'a>> {
+        let start = self.pos;
+        self.pos += 2;
+        let mut bytes = Vec::new();
+        loop {
+            let at = self.pos;
+            match self.bump() {
+                None => return Err(self.error(TokenKind::Char('\"'), "unterminated byte string")),
+                Some('\"') => break,
+                Some('\\') => bytes.push(self.parse_byte_escape(at)?),
+                Some(c) if c.is_ascii() => bytes.push(c as u8),
+                Some(c) => {
+                    return Err(self.error_at(
+                        at,
+                        TokenKind::Escape,
+                        format!("{c:?} in a byte string"),
+                    ))
+                }
             }
         }
-        let end = self.pos;
-        self.pos += 1;
+        Ok(Token {
+            typ: TokenType::Bytes(bytes),
+            val: &self.buf[start..self.pos],
+        })
+    }
 
-        // println!("found string from {} to {}", start, self.pos);
-        // panic!();
+    /// Parses the escape sequence of a byte string starting with the backslash at `start`.
+    fn parse_byte_escape(&mut self, start: usize) -> Result<u8> {
+        let escaped = match self.bump() {
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('n') => b'\n',
+            Some('\'') => b'\'',
+            Some('\"') => b'\"',
+            Some('\\') => b'\\',
+            Some('x') => {
+                let hex = self
+                    .buf
+                    .get(self.pos..self.pos + 2)
+                    .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                let Some(byte) = hex else {
+                    return Err(self.error_at(start, TokenKind::Escape, "invalid hex escape"));
+                };
+                self.pos += 2;
+                byte
+            }
+            None => return Err(self.error(TokenKind::Char('\"'), "unterminated byte string")),
+            Some(_) => return Err(self.error_at(start, TokenKind::Escape, "unknown escape")),
+        };
+        Ok(escaped)
+    }
 
+    fn parse_string(&mut self) -> Result<Token<'a>> {
+        let start = self.pos;
+        let string = self.parse_str_literal()?;
         Ok(Token {
-            typ: TokenType::String,
-            val: &self.buf[start..end],
+            typ: TokenType::String(string),
+            val: &self.buf[start..self.pos],
         })
     }
 
+    /// Parses a string literal like `"a \"quoted\" word"`, which holds any characters and the
+    /// escape sequences `\t`, `\r`, `\n`, `\0`, `\'`, `\"`, `\\` and `\u{NNNN}`.
+    fn parse_str_literal(&mut self) -> Result<String> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            string.push_str(self.skip_while(|c| c != '\"' && c != '\\'));
+            let at = self.pos;
+            match self.bump() {
+                None => return Err(self.error(TokenKind::Char('\"'), "unterminated string")),
+                Some('\"') => return Ok(string),
+                Some(_) => string.push(self.parse_char_escape(at)?),
+            }
+        }
+    }
+
+    /// Parses the escape sequence of a string starting with the backslash at `start`.
+    fn parse_char_escape(&mut self, start: usize) -> Result<char> {
+        let escaped = match self.bump() {
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('n') => '\n',
+            Some('0') => '\0',
+            Some('\'') => '\'',
+            Some('\"') => '\"',
+            Some('\\') => '\\',
+            Some('u') => {
+                let Some(digits) = self
+                    .rest()
+                    .strip_prefix('{')
+                    .and_then(|r| r.split_once('}'))
+                    .map(|(digits, _)| digits)
+                    .filter(|d| {
+                        (1..=6).contains(&d.len()) && d.bytes().all(|b| b.is_ascii_hexdigit())
+                    })
+                else {
+                    return Err(self.error_at(start, TokenKind::Escape, "invalid unicode escape"));
+                };
+                let Some(c) = u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                else {
+                    return Err(self.error_at(start, TokenKind::Escape, "invalid code point"));
+                };
+                self.pos += digits.len() + 2;
+                c
+            }
+            None => return Err(self.error(TokenKind::Char('\"'), "unterminated string")),
+            Some(_) => return Err(self.error_at(start, TokenKind::Escape, "unknown escape")),
+        };
+        Ok(escaped)
+    }
+
     fn parse_wildcard(&mut self) -> Token<'a> {
         let start = self.pos;
         self.pos += 1;
         Token {
             typ: TokenType::Wildcard,
-            val: &self.buf[start - 1..self.pos],
+            val: &self.buf[start..self.pos],
+        }
+    }
+
+    /// Parses a range, which is either a comparison like `>10` or `<="m"` or has an upper bound
+    /// like `..5`, `..=5` or `>1..5`.
+    fn parse_range(&mut self) -> Result<Token<'a>> {
+        let start = self.pos;
+        let lo = if self.eat('>') {
+            if self.eat('=') {
+                Bound::Included(self.parse_bound()?)
+            } else {
+                Bound::Excluded(self.parse_bound()?)
+            }
+        } else if self.eat('<') {
+            let hi = if self.eat('=') {
+                Bound::Included(self.parse_bound()?)
+            } else {
+                Bound::Excluded(self.parse_bound()?)
+            };
+            return Ok(self.constraint_token(start, Constraint::Range(Bound::Unbounded, hi)));
+        } else if self.rest().starts_with("..") {
+            Bound::Unbounded
+        } else {
+            return Err(self.error_at(start + 1, TokenKind::Char('.'), "incomplete range"));
+        };
+        let hi = if self.rest().starts_with("..") {
+            self.parse_upper_bound()?
+        } else {
+            Bound::Unbounded
+        };
+        Ok(self.constraint_token(start, Constraint::Range(lo, hi)))
+    }
+
+    /// Parses the upper bound of a range, starting at its two dots.
+    fn parse_upper_bound(&mut self) -> Result<Bound<E>> {
+        self.pos += 2;
+        if self.eat('=') {
+            Ok(Bound::Included(self.parse_bound()?))
+        } else if self.at_element_end() {
+            Ok(Bound::Unbounded)
+        } else {
+            Ok(Bound::Excluded(self.parse_bound()?))
+        }
+    }
+
+    /// Parses the value bounding a range.
+    fn parse_bound(&mut self) -> Result<E> {
+        if self.at_element_end() {
+            return Err(self.error(TokenKind::Bound, "missing range bound"));
         }
+        let start = self.pos;
+        let token = self.parse_value()?;
+        self.bound_from_token(start, &token)
+    }
+
+    fn bound_from_token(&self, start: usize, token: &Token<'a>) -> Result<E> {
+        let e = Self::from_token(token);
+        if e.is_defined() {
+            Ok(e)
+        } else {
+            Err(self.error_at(start, TokenKind::Bound, "range bounds must be values"))
+        }
+    }
+
+    /// Returns true if the current element ends here.
+    fn at_element_end(&self) -> bool {
+        self.peek()
+            .is_none_or(|c| matches!(c, ',' | ')' | ']') || c.is_whitespace())
     }
 
-    fn parse_tuple(&mut self, chars: &[char]) -> Result<Token<'a>> {
+    fn parse_pattern(&mut self) -> Result<Token<'a>> {
         let start = self.pos;
+        let is_prefix = self.peek() == Some('^');
         self.pos += 1;
-        let mut tuple_items: Vec<Token<'a>> = Vec::new();
-        while chars[self.pos] != ')' {
-            let token = self.match_next(chars)?;
-            tuple_items.push(token);
+        if self.peek() != Some('\"') {
+            return Err(self.error(TokenKind::Char('\"'), "patterns must be strings"));
+        }
+        let pattern = self.parse_str_literal()?;
+        let constraint = if is_prefix {
+            Constraint::Prefix(pattern)
+        } else {
+            let regex = Regex::new(&pattern).map_err(|e| {
+                self.error_at(
+                    start,
+                    TokenKind::Regex,
+                    format!("invalid regular expression: {e}"),
+                )
+            })?;
+            Constraint::Regex(regex)
+        };
+        Ok(self.constraint_token(start, constraint))
+    }
 
-            if self.pos >= chars.len() {
-                eprintln!("error: incomplete tuple");
-                return Err(ParseError);
+    /// Parses a set of values like `in [1, 2, 3]`, starting after the `in`.
+    fn parse_set(&mut self, start: usize) -> Result<Token<'a>> {
+        self.skip_while(char::is_whitespace);
+        if !self.eat('[') {
+            return Err(self.error(TokenKind::Char('['), "sets must be enclosed in brackets"));
+        }
+        let mut elements = Vec::new();
+        loop {
+            self.skip_separators();
+            match self.peek() {
+                None => return Err(self.error(TokenKind::Char(']'), "unterminated set")),
+                Some(']') => break,
+                Some(_) => {
+                    let at = self.pos;
+                    let e = Self::from_token(&self.match_next()?);
+                    if !e.is_defined() {
+                        return Err(self.error_at(
+                            at,
+                            TokenKind::Element,
+                            "sets can only hold values",
+                        ));
+                    }
+                    elements.push(e);
+                }
             }
-            // eprintln!("pointer: {}", self.pos);
         }
         self.pos += 1;
+        Ok(self.constraint_token(start, Constraint::OneOf(elements)))
+    }
+
+    fn constraint_token(&self, start: usize, constraint: Constraint) -> Token<'a> {
+        Token {
+            typ: TokenType::Constraint(constraint),
+            val: &self.buf[start..self.pos],
+        }
+    }
+
+    fn parse_formal(&mut self) -> Result<Token<'a>> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.skip_while(|c| c.is_ascii_alphanumeric());
+        match name.parse::<Type>() {
+            Ok(typ) => Ok(Token {
+                typ: TokenType::Formal(typ),
+                val: &self.buf[start..self.pos],
+            }),
+            Err(()) => Err(self.error_at(start, TokenKind::Type, format!("unknown type ?{name}"))),
+        }
+    }
+
+    fn parse_tuple(&mut self) -> Result<Token<'a>> {
+        let start = self.pos;
+        let items = self.parse_items()?;
         Ok(Token {
-            typ: TokenType::Tuple(tuple_items),
+            typ: TokenType::Tuple(items),
             val: &self.buf[start..self.pos],
         })
     }
 
+    /// Parses the elements of a tuple, starting at its opening parenthesis.
+    fn parse_items(&mut self) -> Result<Vec<Token<'a>>> {
+        self.pos += 1;
+        let mut tuple_items: Vec<Token<'a>> = Vec::new();
+        loop {
+            self.skip_separators();
+            match self.peek() {
+                None => return Err(self.error(TokenKind::Char(')'), "unterminated tuple")),
+                Some(')') => break,
+                Some(_) => tuple_items.push(self.match_next()?),
+            }
+        }
+        self.pos += 1;
+        Ok(tuple_items)
+    }
+
     fn from_token(token: &Token<'a>) -> E {
         match token {
             Token {
-                typ: TokenType::Integer,
-                val,
-            } => E::I(val.parse::<i32>().unwrap()),
+                typ: TokenType::Integer(i),
+                val: _,
+            } => E::I(*i),
             Token {
-                typ: TokenType::Float,
-                val,
-            } => E::D(val.parse::<f64>().unwrap()),
+                typ: TokenType::Long(l),
+                val: _,
+            } => E::L(*l),
             Token {
-                typ: TokenType::String,
+                typ: TokenType::Unsigned(u),
+                val: _,
+            } => E::U(*u),
+            Token {
+                typ: TokenType::Bool,
                 val,
-            } => E::S((*val).to_string()),
+            } => E::B(*val == "true"),
+            Token {
+                typ: TokenType::Bytes(bytes),
+                val: _,
+            } => E::R(bytes.clone()),
+            Token {
+                typ: TokenType::Float(d),
+                val: _,
+            } => E::D(*d),
+            Token {
+                typ: TokenType::String(s),
+                val: _,
+            } => E::S(s.clone()),
             Token {
                 typ: TokenType::Wildcard,
                 val: _,
             } => E::Any,
+            Token {
+                typ: TokenType::Nil,
+                val: _,
+            } => E::None,
+            Token {
+                typ: TokenType::Formal(typ),
+                val: _,
+            } => E::AnyOf(*typ),
+            Token {
+                typ: TokenType::Constraint(constraint),
+                val: _,
+            } => E::Where(Box::new(constraint.clone())),
             Token {
                 typ: TokenType::Tuple(tokenlist),
                 val: _,
@@ -207,3 +716,211 @@ impl<'a> Lexer<'a> {
         }
     }
 }
+
+/// Where the scanner of a `StreamLexer` is within the current tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scan {
+    /// Outside of string literals.
+    Plain,
+    /// Inside a string or byte string literal.
+    Quoted,
+    /// Right after a backslash inside a literal.
+    Escaped,
+}
+
+/// A lexer that is fed bytes as they come in, e.g. from a socket.
+///
+/// Each tuple is handed out as soon as it is complete. Every byte is scanned once to find the
+/// end of its tuple, and every complete tuple is parsed once. Error offsets count the bytes since
+/// the start of the stream.
+#[derive(Debug)]
+pub struct StreamLexer {
+    buf:      Vec<u8>,
+    /// The number of bytes dropped from the front of `buf`.
+    consumed: usize,
+    /// The start of the tuple that is being scanned.
+    start:    usize,
+    /// The next byte to scan.
+    pos:      usize,
+    depth:    usize,
+    scan:     Scan,
+    /// The results scanned by `finish` that have not been handed out yet.
+    ready:    VecDeque<Result<Tuple>>,
+}
+
+impl Default for StreamLexer {
+    fn default() -> StreamLexer {
+        StreamLexer::new()
+    }
+}
+
+impl StreamLexer {
+    #[must_use]
+    pub const fn new() -> StreamLexer {
+        StreamLexer {
+            buf:      Vec::new(),
+            consumed: 0,
+            start:    0,
+            pos:      0,
+            depth:    0,
+            scan:     Scan::Plain,
+            ready:    VecDeque::new(),
+        }
+    }
+
+    /// Appends bytes to the input. A tuple, or even a character, may be split across several
+    /// calls.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        // drop the tuples that have been handed out already
+        self.buf.drain(..self.start);
+        self.consumed += self.start;
+        self.pos -= self.start;
+        self.start = 0;
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Returns the next tuple of the input, or `None` until more input completes it.
+    /// Invalid tuples are skipped after returning their error.
+    pub fn next_tuple(&mut self) -> Option<result::Result<Tuple, Error>> {
+        let result = self.ready.pop_front().or_else(|| self.scan())?;
+        Some(result.map_err(Error::Parse))
+    }
+
+    fn scan(&mut self) -> Option<Result<Tuple>> {
+        while let Some(&b) = self.buf.get(self.pos) {
+            self.pos += 1;
+            match (self.scan, b) {
+                (Scan::Escaped, _) => self.scan = Scan::Quoted,
+                (Scan::Quoted, b'\\') => self.scan = Scan::Escaped,
+                (Scan::Quoted, b'\"') => self.scan = Scan::Plain,
+                (Scan::Quoted, _) => {}
+                (Scan::Plain, b'(') => self.depth += 1,
+                (Scan::Plain, b')') if self.depth > 1 => self.depth -= 1,
+                (Scan::Plain, b')') if self.depth == 1 => {
+                    self.depth = 0;
+                    let start = mem::replace(&mut self.start, self.pos);
+                    return Some(self.parse(start, self.pos));
+                }
+                (Scan::Plain, b'\"') if self.depth > 0 => self.scan = Scan::Quoted,
+                (Scan::Plain, _) if self.depth > 0 => {}
+                (Scan::Plain, b) if b == b',' || b.is_ascii_whitespace() => self.start = self.pos,
+                (Scan::Plain, _) => return Some(Err(self.skip_invalid())),
+            }
+        }
+        None
+    }
+
+    /// Ends the input, returning an error if it ends within a tuple. Tuples that were complete
+    /// but not handed out yet are still returned by `next_tuple`.
+    ///
+    /// # Errors
+    /// `Error::Parse` for the incomplete tuple at the end of the input.
+    pub fn finish(&mut self) -> result::Result<(), Error> {
+        while let Some(result) = self.scan() {
+            self.ready.push_back(result);
+        }
+        let end = self.buf.len();
+        let rest = &self.buf[self.start..];
+        let result = if rest.iter().all(|b| *b == b',' || b.is_ascii_whitespace()) {
+            Ok(())
+        } else {
+            let offset = self.consumed + end;
+            self.parse(self.start, end).and_then(|_| {
+                Err(ParseError {
+                    offset,
+                    expected: TokenKind::Char(')'),
+                    message: "unterminated tuple".to_string(),
+                })
+            })
+        };
+        *self = StreamLexer {
+            consumed: self.consumed + end,
+            ready: mem::take(&mut self.ready),
+            ..StreamLexer::new()
+        };
+        Ok(result?)
+    }
+
+    fn parse(&self, start: usize, end: usize) -> Result<Tuple> {
+        let offset = self.consumed + start;
+        let input = std::str::from_utf8(&self.buf[start..end]).map_err(|e| ParseError {
+            offset:   offset + e.valid_up_to(),
+            expected: TokenKind::Element,
+            message:  "invalid UTF-8".to_string(),
+        })?;
+        parse_single(input).map_err(|mut e| {
+            e.offset += offset;
+            e
+        })
+    }
+
+    /// Skips the invalid input before the next tuple, starting at the byte that was just scanned.
+    fn skip_invalid(&mut self) -> ParseError {
+        let at = self.pos - 1;
+        let c = String::from_utf8_lossy(&self.buf[at..self.buf.len().min(at + 4)])
+            .chars()
+            .next()
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.pos += self.buf[self.pos..]
+            .iter()
+            .position(|b| *b == b'(' || *b == b',' || b.is_ascii_whitespace())
+            .unwrap_or(self.buf.len() - self.pos);
+        self.start = self.pos;
+        ParseError {
+            offset:   self.consumed + at,
+            expected: TokenKind::Tuple,
+            message:  format!("unexpected {c:?}"),
+        }
+    }
+}
+
+/// Reads the tuples of a reader, like a file or a socket, as they come in.
+///
+/// Reading continues with the next tuple after an `Error::Parse`, but stops after an `Error::Io`.
+pub struct TupleReader<R> {
+    reader: R,
+    lexer:  StreamLexer,
+    chunk:  Box<[u8]>,
+    done:   bool,
+}
+
+impl<R: io::Read> TupleReader<R> {
+    #[must_use]
+    pub fn new(reader: R) -> TupleReader<R> {
+        TupleReader {
+            reader,
+            lexer: StreamLexer::new(),
+            chunk: vec![0; 64 * 1024].into_boxed_slice(),
+            done: false,
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for TupleReader<R> {
+    type Item = result::Result<Tuple, Error>;
+
+    fn next(&mut self) -> Option<result::Result<Tuple, Error>> {
+        loop {
+            if let Some(result) = self.lexer.next_tuple() {
+                return Some(result);
+            }
+            if self.done {
+                return None;
+            }
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    if let Err(e) = self.lexer.finish() {
+                        return Some(Err(e));
+                    }
+                }
+                Ok(n) => self.lexer.feed(&self.chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(Error::Io(e)));
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,6 @@
 //! # Rustupolis
 //!
 //! A tuple space implementation for Rust.
-//!
 
 #![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
 #![allow(
@@ -13,24 +12,28 @@
     clippy::cast_sign_loss,
     clippy::use_self
 )]
-// `error_chain!` can recurse deeply
-#![recursion_limit = "1024"]
-
-#[macro_use]
-extern crate error_chain;
 #[macro_use]
 extern crate log;
 extern crate pretty_env_logger;
 
 extern crate futures;
-extern crate indextree_ng;
+extern crate futures_timer;
+extern crate regex;
 
 #[macro_use]
 pub mod tuple;
+pub mod constraint;
+pub mod convert;
 pub mod error;
+pub mod eval;
 pub mod lexing;
+pub mod persistent;
 pub mod space;
 pub mod store;
 pub mod wildcard;
 
+pub use error::Error;
 pub use tuple::Tuple;
+
+#[cfg(feature = "derive")]
+pub use rustupolis_derive::{FromTuple, IntoTuple};
@@ -0,0 +1,666 @@
+//! Module Persistent
+//!
+//! A store that keeps its tuples in an in-memory store and records every change in a
+//! write-ahead log on the local disk, so that the tuples survive a restart or a crash.
+//!
+//! The log is a sequence of records, each made of the length of its payload and the CRC-32 of
+//! the payload as little-endian `u32`s, followed by the payload. Tuples are written in their
+//! display format. A record that was only partially written when the process died fails its
+//! checksum, and the log is truncated before it when the store is opened again. A damaged record
+//! anywhere before the last one is reported as an error instead.
+//!
+//! The log grows with every change until it is compacted, which replaces it with a snapshot of
+//! the tuples in the store.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::lexing::parse_tuple;
+use rand::seq::SliceRandom;
+
+use crate::store::{Key, Lease, Policy, Store};
+use crate::tuple::Tuple;
+
+/// The size of the length and checksum in front of every record.
+const HEADER: usize = 8;
+
+/// The time to live in the in-memory store of the tuples put in without one, and the longest
+/// time to live that is recorded in the log. Longer leases expire after a century on replay.
+const FOREVER: Duration = Duration::from_hours(100 * 365 * 24);
+
+/// When the log is flushed to the disk.
+///
+/// Every change is written to the log before the operation returns, so that killing the process
+/// never loses an acknowledged change. Changes that have not been flushed yet can only be lost if
+/// the operating system crashes or the power goes out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fsync {
+    /// Flush after every change.
+    Always,
+    /// Flush after every n changes, and when the store is dropped.
+    Batched(usize),
+    /// Leave flushing to the operating system.
+    Never,
+}
+
+/// A change recorded in the log. Copies of tuples and their leases are referred to by the number
+/// of the `Out` record of the copy, counting from 1, which a compaction keeps.
+#[derive(Debug)]
+enum Record {
+    /// A tuple was put in, with the expiry of its lease if it has one.
+    Out(Tuple, Option<SystemTime>),
+    /// The copy with the number is in the store, with the expiry of its lease if it has one.
+    /// Written by a compaction, along with the number of tuples put in before it.
+    Copy(u64, Tuple, Option<SystemTime>),
+    /// The number of tuples that had been put in when the log was compacted.
+    Outs(u64),
+    /// The copy was taken out.
+    In(u64),
+    /// A lease was renewed until the given time.
+    Renew(u64, SystemTime),
+    /// A lease was cancelled.
+    Cancel(u64),
+    /// The expired tuples were removed.
+    Purge,
+}
+
+impl Record {
+    /// Appends the record with its length and checksum to the buffer.
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        buf.extend_from_slice(&[0; HEADER]);
+        match self {
+            Record::Out(tup, None) => {
+                buf.push(b'o');
+                buf.extend_from_slice(tup.to_string().as_bytes());
+            }
+            Record::Out(tup, Some(expiry)) => {
+                buf.push(b'l');
+                buf.extend_from_slice(&millis(*expiry).to_le_bytes());
+                buf.extend_from_slice(tup.to_string().as_bytes());
+            }
+            Record::Copy(out, tup, None) => {
+                buf.push(b'O');
+                buf.extend_from_slice(&out.to_le_bytes());
+                buf.extend_from_slice(tup.to_string().as_bytes());
+            }
+            Record::Copy(out, tup, Some(expiry)) => {
+                buf.push(b'L');
+                buf.extend_from_slice(&out.to_le_bytes());
+                buf.extend_from_slice(&millis(*expiry).to_le_bytes());
+                buf.extend_from_slice(tup.to_string().as_bytes());
+            }
+            Record::Outs(outs) => {
+                buf.push(b'n');
+                buf.extend_from_slice(&outs.to_le_bytes());
+            }
+            Record::In(out) => {
+                buf.push(b'i');
+                buf.extend_from_slice(&out.to_le_bytes());
+            }
+            Record::Renew(out, expiry) => {
+                buf.push(b'r');
+                buf.extend_from_slice(&out.to_le_bytes());
+                buf.extend_from_slice(&millis(*expiry).to_le_bytes());
+            }
+            Record::Cancel(out) => {
+                buf.push(b'c');
+                buf.extend_from_slice(&out.to_le_bytes());
+            }
+            Record::Purge => buf.push(b'p'),
+        }
+        let payload = &buf[start + HEADER..];
+        let len = (payload.len() as u32).to_le_bytes();
+        let crc = crc32fast::hash(payload).to_le_bytes();
+        buf[start..start + 4].copy_from_slice(&len);
+        buf[start + 4..start + HEADER].copy_from_slice(&crc);
+    }
+
+    /// Reads a record from a payload that passed its checksum.
+    fn decode(payload: &[u8]) -> Result<Record, Error> {
+        let (&kind, rest) = payload.split_first().ok_or_else(corrupt)?;
+        match kind {
+            b'o' => Ok(Record::Out(tuple_at(rest)?, None)),
+            b'l' => Ok(Record::Out(
+                tuple_at(rest.get(8..).ok_or_else(corrupt)?)?,
+                Some(time_at(rest)?),
+            )),
+            b'O' => Ok(Record::Copy(
+                u64_at(rest)?,
+                tuple_at(rest.get(8..).ok_or_else(corrupt)?)?,
+                None,
+            )),
+            b'L' => Ok(Record::Copy(
+                u64_at(rest)?,
+                tuple_at(rest.get(16..).ok_or_else(corrupt)?)?,
+                Some(time_at(rest.get(8..).ok_or_else(corrupt)?)?),
+            )),
+            b'n' => Ok(Record::Outs(u64_at(rest)?)),
+            b'i' => Ok(Record::In(u64_at(rest)?)),
+            b'r' => Ok(Record::Renew(
+                u64_at(rest)?,
+                time_at(rest.get(8..).ok_or_else(corrupt)?)?,
+            )),
+            b'c' => Ok(Record::Cancel(u64_at(rest)?)),
+            b'p' => Ok(Record::Purge),
+            _ => Err(corrupt()),
+        }
+    }
+}
+
+fn corrupt() -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "corrupt write-ahead log record",
+    ))
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+fn u64_at(bytes: &[u8]) -> Result<u64, Error> {
+    let bytes = bytes.get(..8).ok_or_else(corrupt)?;
+    Ok(u64::from_le_bytes(bytes.try_into().map_err(|_| corrupt())?))
+}
+
+fn time_at(bytes: &[u8]) -> Result<SystemTime, Error> {
+    Ok(UNIX_EPOCH + Duration::from_millis(u64_at(bytes)?))
+}
+
+fn tuple_at(bytes: &[u8]) -> Result<Tuple, Error> {
+    parse_tuple(std::str::from_utf8(bytes).map_err(|_| corrupt())?)
+}
+
+/// Returns the payload of the complete record with a valid checksum that starts at the offset.
+fn record_at(log: &[u8], start: usize) -> Option<&[u8]> {
+    let header = log.get(start..start + HEADER)?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let payload = log.get(start + HEADER..start + HEADER + len)?;
+    (!payload.is_empty() && crc32fast::hash(payload) == crc).then_some(payload)
+}
+
+/// Splits the log into the payloads of its records and the length of the part that holds only
+/// complete records with a valid checksum. Only the last record may be incomplete or damaged, as
+/// it is when its write was cut short: the damage is an error if a valid record starts anywhere
+/// after it, as one does after a damaged length.
+fn payloads(log: &[u8]) -> Result<(Vec<&[u8]>, usize), Error> {
+    let mut payloads = Vec::new();
+    let mut valid = 0;
+    while valid < log.len() {
+        if let Some(payload) = record_at(log, valid) {
+            payloads.push(payload);
+            valid += HEADER + payload.len();
+        } else if (valid + 1..log.len()).any(|start| record_at(log, start).is_some()) {
+            return Err(corrupt());
+        } else {
+            break;
+        }
+    }
+    Ok((payloads, valid))
+}
+
+/// How long from now until the time, or zero if it has passed.
+fn until(time: SystemTime) -> Duration {
+    time.duration_since(SystemTime::now()).unwrap_or_default()
+}
+
+/// A store that keeps its tuples in another, in-memory store and appends every change to a
+/// write-ahead log, which is replayed into the in-memory store when the log is opened again.
+///
+/// Tuples are handed out in the order of the policy of the in-memory store. The expiry of a
+/// lease is recorded in wall-clock time, so leases keep running while the store is closed.
+///
+/// Every copy is leased in the in-memory store, the ones put in without a time to live for a
+/// century, so that the copy that is taken out can be removed by its lease and logged by its
+/// number. Of the copies of a tuple, the oldest one is taken first, the newest one if the
+/// in-memory store hands out tuples in `Policy::Lifo` and any one in `Policy::Random`.
+#[allow(clippy::module_name_repetitions)]
+pub struct PersistentStore<S: Store> {
+    store:    S,
+    path:     PathBuf,
+    log:      File,
+    /// The length of the log up to the last complete record.
+    len:      u64,
+    fsync:    Fsync,
+    unsynced: usize,
+    /// The number of tuples that have been put in.
+    outs:     u64,
+    /// The copies of the tuples in the in-memory store, by the number of the copy.
+    copies:   HashMap<Key<Tuple>, BTreeMap<u64, Stored>>,
+}
+
+/// A copy of a tuple in the in-memory store.
+struct Stored {
+    /// The id of the lease of the in-memory store on the copy.
+    id:     u64,
+    /// The expiry of the lease, or `None` if the tuple was put in without a time to live.
+    expiry: Option<SystemTime>,
+}
+
+impl<S: Store> PersistentStore<S> {
+    /// Opens the log at the path, creating it if it does not exist, and replays it into the
+    /// store, which should be empty. A partially written record at the end of the log is
+    /// discarded.
+    ///
+    /// # Errors
+    /// `Error::Io` if the log cannot be read or written or a record before the last one is
+    /// damaged, and `Error::Io` or `Error::Parse` if it holds a complete record that cannot be
+    /// decoded.
+    pub fn open<P: AsRef<Path>>(path: P, store: S, fsync: Fsync) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let created = !path.exists();
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        if created && fsync != Fsync::Never {
+            sync_dir(path)?;
+        }
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        let (payloads, valid) = payloads(&bytes)?;
+        if valid < bytes.len() {
+            warn!(
+                "discarding {} bytes of an incomplete record at the end of {}",
+                bytes.len() - valid,
+                path.display()
+            );
+            log.set_len(valid as u64)?;
+            log.sync_data()?;
+        }
+        let records = payloads
+            .into_iter()
+            .map(Record::decode)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut persistent = PersistentStore {
+            store,
+            path: path.to_path_buf(),
+            log,
+            len: valid as u64,
+            fsync,
+            unsynced: 0,
+            outs: 0,
+            copies: HashMap::new(),
+        };
+        persistent.replay(records)?;
+        debug!(
+            "replayed {} tuples from {}",
+            persistent.outs,
+            path.display()
+        );
+        Ok(persistent)
+    }
+
+    /// The in-memory store holding the tuples.
+    #[must_use]
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Flushes the log to the disk.
+    ///
+    /// # Errors
+    /// `Error::Io` if the log cannot be flushed.
+    pub fn sync(&mut self) -> Result<(), Error> {
+        self.log.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Replaces the log with a snapshot of the copies of the tuples in the store, which keep
+    /// their numbers so that the leases handed out before stay valid. The snapshot is written to
+    /// a new file next to the log and flushed before it takes the place of the log, so that a
+    /// crash leaves either the old or the new log behind.
+    ///
+    /// # Errors
+    /// `Error::Io` if the snapshot cannot be written or cannot replace the log, which is then
+    /// kept as it was.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        let mut copies = self
+            .copies
+            .iter()
+            .flat_map(|(key, copies)| {
+                copies
+                    .iter()
+                    .map(|(&out, stored)| (out, &key.0, stored.expiry))
+            })
+            .collect::<Vec<_>>();
+        copies.sort_unstable_by_key(|&(out, ..)| out);
+        let mut buf = Vec::new();
+        Record::Outs(self.outs).encode(&mut buf);
+        for (out, tup, expiry) in copies {
+            Record::Copy(out, tup.clone(), expiry).encode(&mut buf);
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".compact");
+        let tmp = PathBuf::from(tmp);
+        let written = File::create(&tmp)
+            .and_then(|mut file| {
+                file.write_all(&buf)?;
+                file.sync_data()
+            })
+            .and_then(|()| fs::rename(&tmp, &self.path));
+        if let Err(e) = written {
+            if let Err(remove) = fs::remove_file(&tmp) {
+                error!("cannot remove {}: {remove}", tmp.display());
+            }
+            return Err(e.into());
+        }
+        sync_dir(&self.path)?;
+        self.log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        debug!(
+            "compacted {} from {} to {} bytes",
+            self.path.display(),
+            self.len,
+            buf.len()
+        );
+        self.len = buf.len() as u64;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Applies the records to the store. Copies that are taken out or cancelled later on are
+    /// left out, and leased tuples are put in with the expiry of their last renewal, as renewals
+    /// are only recorded for tuples that have not expired yet.
+    fn replay(&mut self, records: Vec<Record>) -> Result<(), Error> {
+        let mut expiries = HashMap::new();
+        let mut removed = HashSet::new();
+        let mut outs = 0;
+        for record in &records {
+            match record {
+                Record::Out(_, expiry) => {
+                    outs += 1;
+                    if let Some(expiry) = expiry {
+                        expiries.insert(outs, *expiry);
+                    }
+                }
+                Record::Copy(out, _, expiry) => {
+                    if let Some(expiry) = expiry {
+                        expiries.insert(*out, *expiry);
+                    }
+                }
+                Record::Outs(n) => outs = *n,
+                Record::Renew(out, expiry) => {
+                    expiries.insert(*out, *expiry);
+                }
+                Record::In(out) | Record::Cancel(out) => {
+                    removed.insert(*out);
+                }
+                Record::Purge => {}
+            }
+        }
+        for record in records {
+            match record {
+                Record::Out(tup, expiry) => {
+                    self.outs += 1;
+                    if !removed.contains(&self.outs) {
+                        let expiry = expiry.map(|_| expiries[&self.outs]);
+                        self.insert(self.outs, tup, expiry.map_or(FOREVER, until), expiry)?;
+                    }
+                }
+                Record::Copy(out, tup, expiry) => {
+                    if !removed.contains(&out) {
+                        let expiry = expiry.map(|_| expiries[&out]);
+                        self.insert(out, tup, expiry.map_or(FOREVER, until), expiry)?;
+                    }
+                }
+                Record::Outs(n) => self.outs = n,
+                Record::Purge => {
+                    self.purge()?;
+                }
+                Record::In(_) | Record::Renew(..) | Record::Cancel(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the records to the log and flushes it as the policy demands. If the records
+    /// cannot be written completely, the log is cut back to its previous length.
+    fn append(&mut self, records: &[Record]) -> Result<(), Error> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut buf = Vec::new();
+        for record in records {
+            record.encode(&mut buf);
+        }
+        if let Err(e) = self.log.write_all(&buf) {
+            if let Err(truncate) = self.log.set_len(self.len) {
+                error!("cannot remove an incomplete record from the log: {truncate}");
+            }
+            return Err(e.into());
+        }
+        self.len += buf.len() as u64;
+        self.unsynced += records.len();
+        match self.fsync {
+            Fsync::Always => self.sync(),
+            Fsync::Batched(n) if self.unsynced >= n => self.sync(),
+            Fsync::Batched(_) | Fsync::Never => Ok(()),
+        }
+    }
+
+    /// Puts the copy of the tuple with the number into the store for `ttl` and keeps track of it,
+    /// with the expiry if the tuple was put in with a time to live. Returns the lease of the
+    /// store on the copy.
+    fn insert(
+        &mut self,
+        out: u64,
+        tup: Tuple,
+        ttl: Duration,
+        expiry: Option<SystemTime>,
+    ) -> Result<Lease, Error> {
+        let lease = self.store.out_with_ttl(tup.clone(), ttl)?;
+        let stored = Stored {
+            id: lease.id(),
+            expiry,
+        };
+        self.copies.entry(Key(tup)).or_default().insert(out, stored);
+        Ok(lease)
+    }
+
+    /// Puts a copy of the tuple into the store, and logs it once the store has accepted it.
+    /// Returns the number of the copy.
+    fn put(&mut self, tup: Tuple, ttl: Option<Duration>) -> Result<u64, Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
+        }
+        let out = self.outs + 1;
+        let expiry = ttl.map(|ttl| SystemTime::now() + ttl.min(FOREVER));
+        let record = Record::Out(tup.clone(), expiry);
+        let lease = self.insert(out, tup, ttl.unwrap_or(FOREVER), expiry)?;
+        if let Err(e) = self.append(&[record]) {
+            self.forget(lease.tuple(), out);
+            self.store.cancel(&lease)?;
+            return Err(e);
+        }
+        self.outs = out;
+        Ok(out)
+    }
+
+    /// Stops keeping track of the copy of the tuple and returns the lease of the store on it.
+    fn forget(&mut self, tup: &Tuple, out: u64) -> Option<Lease> {
+        let key = Key(tup.clone());
+        let copies = self.copies.get_mut(&key)?;
+        let stored = copies.remove(&out)?;
+        if copies.is_empty() {
+            self.copies.remove(&key);
+        }
+        Some(Lease::new(key.0, stored.id))
+    }
+
+    /// Returns the copy of the leased tuple if it has not been taken out, cancelled or purged
+    /// yet.
+    fn leased(&self, lease: &Lease) -> Option<&Stored> {
+        self.copies
+            .get(&Key(lease.tuple().clone()))?
+            .get(&lease.id())
+            .filter(|stored| stored.expiry.is_some())
+    }
+
+    /// Logs the copy of the tuple that comes first in the policy of the store before taking it
+    /// out. Copies that turn out to have expired are logged and forgotten on the way. Returns
+    /// false if all of them have expired.
+    fn take(&mut self, tup: &Tuple) -> Result<bool, Error> {
+        let mut outs = self
+            .copies
+            .get(&Key(tup.clone()))
+            .map(|copies| copies.keys().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        match self.store.policy() {
+            Policy::Lifo => outs.reverse(),
+            Policy::Random => outs.shuffle(&mut rand::rng()),
+            Policy::Fifo | Policy::Ordered => {}
+        }
+        for out in outs {
+            self.append(&[Record::In(out)])?;
+            if let Some(lease) = self.forget(tup, out) {
+                if self.store.cancel(&lease)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Removes the expired tuples from the store and forgets their copies.
+    fn purge(&mut self) -> Result<usize, Error> {
+        let now = SystemTime::now();
+        self.copies.retain(|_, copies| {
+            copies.retain(|_, stored| stored.expiry.is_none_or(|expiry| expiry > now));
+            !copies.is_empty()
+        });
+        self.store.purge_expired()
+    }
+}
+
+/// Flushes the directory holding the file, so that a newly created file is not lost.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<(), Error> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all()?,
+        _ => File::open(".")?.sync_all()?,
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+impl<S: Store> Drop for PersistentStore<S> {
+    fn drop(&mut self) {
+        if self.unsynced > 0 && self.fsync != Fsync::Never {
+            if let Err(e) = self.sync() {
+                error!("cannot flush the log: {e}");
+            }
+        }
+    }
+}
+
+/// Implements the store trait for `PersistentStore`.
+impl<S: Store> Store for PersistentStore<S> {
+    /// Inserts the tuple into the store if it is defined, and logs it once the store has
+    /// accepted it.
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
+        self.put(tup, None)?;
+        Ok(())
+    }
+
+    /// Inserts the tuple into the store if it is defined, and logs it with its expiry once the
+    /// store has accepted it.
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error> {
+        let out = self.put(tup.clone(), Some(ttl))?;
+        Ok(Lease::new(tup, out))
+    }
+
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        let Some(stored) = self.leased(lease) else {
+            return Ok(false);
+        };
+        let inner = Lease::new(lease.tuple().clone(), stored.id);
+        if !self.store.renew(&inner, ttl)? {
+            self.forget(lease.tuple(), lease.id());
+            return Ok(false);
+        }
+        let expiry = SystemTime::now() + ttl.min(FOREVER);
+        self.append(&[Record::Renew(lease.id(), expiry)])?;
+        if let Some(stored) = self
+            .copies
+            .get_mut(&Key(lease.tuple().clone()))
+            .and_then(|copies| copies.get_mut(&lease.id()))
+        {
+            stored.expiry = Some(expiry);
+        }
+        Ok(true)
+    }
+
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        if self.leased(lease).is_none() {
+            return Ok(false);
+        }
+        self.append(&[Record::Cancel(lease.id())])?;
+        match self.forget(lease.tuple(), lease.id()) {
+            Some(inner) => self.store.cancel(&inner),
+            None => Ok(false),
+        }
+    }
+
+    fn purge_expired(&mut self) -> Result<usize, Error> {
+        self.append(&[Record::Purge])?;
+        self.purge()
+    }
+
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.store.rdp(tup)
+    }
+
+    /// Logs the copy of the tuple matching tup that comes first in the policy of the store
+    /// before taking it out.
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        match self.store.rdp(tup)? {
+            Some(found) if self.take(&found)? => Ok(Some(found)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Logs the copies of all tuples matching tup before taking them out.
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        let found = self.store.rd_all(tup)?;
+        let mut outs = BTreeSet::new();
+        for m in &found {
+            if let Some(copies) = self.copies.get(&Key(m.clone())) {
+                outs.extend(copies.keys().copied());
+            }
+        }
+        let records = outs.into_iter().map(Record::In).collect::<Vec<_>>();
+        self.append(&records)?;
+        for m in &found {
+            let copies = self.copies.remove(&Key(m.clone())).unwrap_or_default();
+            for stored in copies.into_values() {
+                self.store.cancel(&Lease::new(m.clone(), stored.id))?;
+            }
+        }
+        Ok(found)
+    }
+
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.store.rd_all(tup)
+    }
+
+    fn policy(&self) -> Policy {
+        self.store.policy()
+    }
+}
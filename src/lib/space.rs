@@ -3,53 +3,162 @@
 //! A space combines a store and concurrent matching to allow for searching
 //! tuples containing wildcards.
 
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::channel::oneshot::{self, channel, Receiver, Sender};
 use futures::future;
+use futures::{FutureExt, Stream};
+use futures_timer::Delay;
 use std::future::Future;
+use std::iter;
+use std::mem;
 use std::pin::Pin;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use crate::error::Error;
-use crate::store::Store;
+use crate::eval::{self, Field};
+use crate::store::{Lease, Policy, Store};
 use crate::tuple::Tuple;
 use crate::wildcard;
 
 /// Matchings can either be pending or completed.
+///
+/// A pending match registers the waker of the task polling it and resolves as soon as a matching
+/// tuple is handed over by `Space::tuple_out`. It resolves to a `WaiterDisconnected` error if
+/// the space is dropped before that happens.
 pub enum Match {
-    Done(Result<Option<Tuple>, Error>),
-    Pending(Receiver<Tuple>),
+    Done(Result<Tuple, Error>),
+    Pending(Waiting),
+}
+
+impl Match {
+    /// Takes the result of a completed match, leaving an error for polls after completion.
+    const fn take(result: &mut Result<Tuple, Error>) -> Result<Tuple, Error> {
+        mem::replace(result, Err(Error::WaiterDisconnected))
+    }
 }
 
 impl Future for Match {
-    type Output = Option<Tuple>;
-
-    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match &*self {
-            Match::Done(Ok(result)) => Poll::Ready(result.clone()),
-            Match::Done(Err(e)) => {
-                eprintln!("error polling Match: {e:?}");
-                Poll::from(None)
-            }
-            // Match::Pending(ref rx) => rx.poll().map_err(|()| "receive failed".into()),
-            // Match::Pending(_) => Poll::Pending,
-            Match::Pending(ref rx) => {
-                let receiver_poll = rx.try_recv();
-                match receiver_poll {
-                    Ok(tup) => Poll::from(Some(tup)),
-                    Err(e) => {
-                        eprintln!("Match::poll encountered error: {e:?}");
-                        Poll::from(None)
-                    }
-                }
-            }
+    type Output = Result<Tuple, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match *self {
+            Match::Done(ref mut result) => Poll::Ready(Match::take(result)),
+            Match::Pending(ref mut waiting) => waiting
+                .poll(cx)
+                .map(|result| result.ok_or(Error::WaiterDisconnected)),
         }
     }
 }
 
+/// A waiter registered in the wildcard tree of a space, waiting for a matching tuple.
+///
+/// Dropping it before a tuple arrived removes the waiter from the tree again, so that later
+/// tuples are not handed over to a match that no longer exists.
+pub struct Waiting {
+    rx:      Receiver<Tuple>,
+    tup:     Tuple,
+    key:     u64,
+    pending: Weak<Mutex<wildcard::Tree<Waiter>>>,
+    done:    bool,
+}
+
+impl Waiting {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Option<Tuple>> {
+        let result = Pin::new(&mut self.rx).poll(cx).map(Result::ok);
+        self.done = result.is_ready();
+        result
+    }
+
+    /// Stops waiting. Returns the tuple if one has been handed over in the meantime.
+    fn cancel(&mut self) -> Option<Tuple> {
+        if !self.done {
+            self.done = true;
+            unregister(&self.pending, &self.tup, self.key);
+        }
+        self.rx.close();
+        self.rx.try_recv().ok().flatten()
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if !self.done {
+            trace!("dropping pending match {:?}", self.tup);
+            self.cancel();
+        }
+    }
+}
+
+/// A stream of all tuples matching a template that are put into the space after subscribing.
+///
+/// Subscribers only observe tuples, they never consume them. Dropping the subscription removes
+/// it from the wildcard tree again.
+pub struct Subscription {
+    rx:      UnboundedReceiver<Tuple>,
+    tup:     Tuple,
+    key:     u64,
+    pending: Weak<Mutex<wildcard::Tree<Waiter>>>,
+}
+
+impl Stream for Subscription {
+    type Item = Tuple;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        trace!("dropping subscription {:?}", self.tup);
+        unregister(&self.pending, &self.tup, self.key);
+    }
+}
+
+/// A match that gives up waiting for a tuple once its timeout has elapsed.
+///
+/// Resolves to a `TimedOut` error if no matching tuple arrived in time.
+pub struct Timeout {
+    matching: Match,
+    delay:    Delay,
+}
+
+impl Future for Timeout {
+    type Output = Result<Tuple, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let waiting = match this.matching {
+            Match::Pending(ref mut waiting) => waiting,
+            Match::Done(ref mut result) => return Poll::Ready(Match::take(result)),
+        };
+        if let Poll::Ready(result) = waiting.poll(cx) {
+            return Poll::Ready(result.ok_or(Error::WaiterDisconnected));
+        }
+        match Pin::new(&mut this.delay).poll(cx) {
+            // a tuple may have been handed over right before the timeout
+            Poll::Ready(()) => Poll::Ready(waiting.cancel().ok_or(Error::TimedOut)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A pending match waiting in the wildcard tree for a matching tuple.
+enum Waiter {
+    /// Registered by `tuple_rd`, receives a copy of the tuple.
+    Read(Sender<Tuple>),
+    /// Registered by `tuple_in`, consumes the tuple.
+    Take(Sender<Tuple>),
+    /// Registered by `subscribe`, receives a copy of every matching tuple until dropped.
+    Notify(UnboundedSender<Tuple>),
+}
+
 /// Space encapsulates the store and a wildcard tree.
 pub struct Space<T: Store> {
-    store: T,
-    pending: wildcard::Tree<Sender<Tuple>>,
+    store:   T,
+    pending: Arc<Mutex<wildcard::Tree<Waiter>>>,
 }
 
 impl<T> Space<T>
@@ -59,7 +168,7 @@ where
     pub fn new(store: T) -> Space<T> {
         Space {
             store,
-            pending: wildcard::Tree::new(),
+            pending: Arc::new(Mutex::new(wildcard::Tree::new())),
         }
     }
 
@@ -67,20 +176,12 @@ where
     pub fn tuple_in(&mut self, tup: Tuple) -> Match {
         trace!("tuple_in");
         match self.store.inp(&tup) {
-            None => {
+            Ok(None) => {
                 trace!("matched Ok(None)");
-                let (tx, rx) = channel();
-                let resultat = self.pending.insert(tup, tx);
-                trace!("resultat {:?}", resultat);
-                if let Err(e) = resultat {
-                    trace!("return match::Done(Err(...))");
-                    Match::Done(Err(Error::with_chain(e, "send failed")))
-                } else {
-                    trace!("return Match::Pending(rx)");
-                    Match::Pending(rx)
-                }
+                self.wait(tup, Waiter::Take)
             }
-            result => Match::Done(Ok(result)),
+            Ok(Some(found)) => Match::Done(Ok(found)),
+            Err(e) => Match::Done(Err(e)),
         }
     }
 
@@ -88,45 +189,685 @@ where
     pub fn tuple_rd(&mut self, tup: Tuple) -> Match {
         trace!("tuple_rd");
         match self.store.rdp(&tup) {
-            None => {
-                let (tx, rx) = channel();
-                if let Err(e) = self.pending.insert(tup, tx) {
-                    Match::Done(Err(Error::with_chain(e, "send failed")))
-                } else {
-                    Match::Pending(rx)
-                }
+            Ok(None) => self.wait(tup, Waiter::Read),
+            Ok(Some(found)) => Match::Done(Ok(found)),
+            Err(e) => Match::Done(Err(e)),
+        }
+    }
+
+    /// Linda `inp`: find a matching tuple, retrieve AND remove it from the space if there is one.
+    /// Never waits and never registers a pending match, returns `None` if there is no match.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_in(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        trace!("try_in");
+        self.store.inp(tup)
+    }
+
+    /// Linda `rdp`: find a matching tuple, retrieve but NOT remove it from the space if there is
+    /// one. Never waits and never registers a pending match, returns `None` if there is no match.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_rd(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        trace!("try_rd");
+        self.store.rdp(tup)
+    }
+
+    /// Like `tuple_in`, but gives up waiting for a matching tuple after `timeout`.
+    pub fn tuple_in_timeout(&mut self, tup: Tuple, timeout: Duration) -> Timeout {
+        Timeout {
+            matching: self.tuple_in(tup),
+            delay:    Delay::new(timeout),
+        }
+    }
+
+    /// Like `tuple_rd`, but gives up waiting for a matching tuple after `timeout`.
+    pub fn tuple_rd_timeout(&mut self, tup: Tuple, timeout: Duration) -> Timeout {
+        Timeout {
+            matching: self.tuple_rd(tup),
+            delay:    Delay::new(timeout),
+        }
+    }
+
+    /// Subscribe to all tuples matching `tup` that are put into the space from now on.
+    /// The tuples are not consumed, they still go to pending matches or into the store.
+    ///
+    /// # Errors
+    /// If the subscription cannot be registered in the wildcard tree.
+    pub fn subscribe(&self, tup: Tuple) -> Result<Subscription, Error> {
+        trace!("subscribe");
+        let (tx, rx) = unbounded();
+        let key = lock(&self.pending).insert(tup.clone(), Waiter::Notify(tx))?;
+        Ok(Subscription {
+            rx,
+            tup,
+            key,
+            pending: Arc::downgrade(&self.pending),
+        })
+    }
+
+    /// Starts a transaction that groups several operations into one atomic unit.
+    /// The space can only be used through the transaction until it is committed or aborted.
+    pub fn transaction(&mut self) -> Transaction<'_, T> {
+        trace!("transaction");
+        Transaction {
+            space:   self,
+            taken:   Vec::new(),
+            written: Vec::new(),
+            done:    false,
+        }
+    }
+
+    /// Registers a waiter for the tuple in the wildcard tree.
+    fn wait(&self, tup: Tuple, waiter: fn(Sender<Tuple>) -> Waiter) -> Match {
+        let (tx, rx) = channel();
+        let resultat = lock(&self.pending).insert(tup.clone(), waiter(tx));
+        trace!("resultat {resultat:?}");
+        match resultat {
+            Ok(key) => {
+                trace!("return Match::Pending(rx)");
+                Match::Pending(Waiting {
+                    rx,
+                    tup,
+                    key,
+                    pending: Arc::downgrade(&self.pending),
+                    done: false,
+                })
+            }
+            Err(e) => {
+                trace!("return match::Done(Err(...))");
+                Match::Done(Err(e))
             }
-            result => Match::Done(Ok(result)),
         }
     }
 
+    /// The order in which the store of the space hands out matching tuples.
+    #[must_use]
+    pub fn policy(&self) -> Policy {
+        self.store.policy()
+    }
+
+    /// Find all matching tuples, retrieve AND remove them from the space at once.
+    /// Never waits, returns an empty list if there are no matches.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn tuple_in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        trace!("tuple_in_all");
+        self.store.in_all(tup)
+    }
+
+    /// Find all matching tuples, retrieve but NOT remove them from the space.
+    /// Never waits, returns an empty list if there are no matches.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn tuple_rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        trace!("tuple_rd_all");
+        self.store.rd_all(tup)
+    }
+
+    /// Linda `collect`: moves all tuples matching `tup` from this space into `dst` and returns
+    /// how many were moved. Matches waiting in `dst` are served as with `tuple_out`. If a tuple
+    /// cannot be put into `dst`, the ones that have not been moved yet are put back.
+    ///
+    /// # Errors
+    /// If the tuples cannot be taken from this space or put into `dst`.
+    pub fn collect<U: Store>(&mut self, dst: &mut Space<U>, tup: &Tuple) -> Result<usize, Error> {
+        let tuples = self.tuple_in_all(tup)?;
+        let count = tuples.len();
+        if let Err((e, unmoved)) = dst.out_all(tuples) {
+            self.put_back(unmoved);
+            return Err(e);
+        }
+        Ok(count)
+    }
+
+    /// Linda `copy-collect`: copies all tuples matching `tup` from this space into `dst` and
+    /// returns how many were copied. Matches waiting in `dst` are served as with `tuple_out`.
+    ///
+    /// # Errors
+    /// If the tuples cannot be read from this space or put into `dst`.
+    pub fn copy_collect<U: Store>(
+        &mut self,
+        dst: &mut Space<U>,
+        tup: &Tuple,
+    ) -> Result<usize, Error> {
+        let tuples = self.tuple_rd_all(tup)?;
+        let count = tuples.len();
+        tuples.into_iter().try_for_each(|t| dst.out(t))?;
+        Ok(count)
+    }
+
     /// Inserts a tuple into the store and returns a match that is
     /// either still pending or done.
-    pub fn tuple_out(&mut self, tup: Tuple) -> Pin<Box<dyn Future<Output = Result<(), Error>>>> {
+    ///
+    /// Every pending `tuple_rd` matching the tuple receives a copy of it. If a `tuple_in` is
+    /// pending as well, the oldest one consumes the tuple, otherwise it is put into the store.
+    /// Waiters whose match has been dropped in the meantime are skipped.
+    pub fn tuple_out(
+        &mut self,
+        tup: Tuple,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
         trace!("tuple_out");
-        // if !tup.is_defined() {
-        //     // Box::new(futures::future::err("undefined tuple".into()))
-        //     // Box::pin(future::err(Error::from("undefined tuple")))
-        //     Box::pin(future::err(Error::with_chain(
-        //         InsertUndefinedTuple,
-        //         "insert undefined tuple",
-        //     )))
-        // } else
-        if let Some(tx) = self.pending.take(tup.clone()) {
-            let send_attempt = tx
-                .send(tup)
-                .map(|_| ())
-                .map_err(|e| Error::with_chain(e, "send failed"));
-
-            Box::pin(future::ready(send_attempt))
-        } else {
-            match self.store.out(tup) {
-                Ok(result) => Box::pin(future::ready(Ok(result))),
-                Err(e) => Box::pin(future::err(Error::with_chain(
-                    e,
-                    "unable to insert tuple into store",
-                ))),
+        Box::pin(future::ready(self.out(tup)))
+    }
+
+    /// Like `tuple_out`, but the tuple expires after `ttl`. Returns a lease on the tuple, or
+    /// `None` if a pending `tuple_in` consumed it right away.
+    ///
+    /// # Errors
+    /// If the tuple is undefined or cannot be put into the store.
+    pub fn tuple_out_with_ttl(
+        &mut self,
+        tup: Tuple,
+        ttl: Duration,
+    ) -> Result<Option<Lease>, Error> {
+        trace!("tuple_out_with_ttl");
+        self.deliver(tup)?
+            .map(|tup| self.store.out_with_ttl(tup, ttl))
+            .transpose()
+    }
+
+    /// Let the leased tuple expire `ttl` from now. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        self.store.renew(lease, ttl)
+    }
+
+    /// Remove the leased tuple from the space. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        self.store.cancel(lease)
+    }
+
+    /// Reclaim the memory of all expired tuples and return how many there were.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn purge_expired(&mut self) -> Result<usize, Error> {
+        self.store.purge_expired()
+    }
+
+    /// Puts the tuples into the space in order. On failure, returns the error along with the
+    /// tuples that have not been put in.
+    fn out_all(&mut self, tuples: Vec<Tuple>) -> Result<(), (Error, Vec<Tuple>)> {
+        let mut tuples = tuples.into_iter();
+        while let Some(tup) = tuples.next() {
+            if let Err(e) = self.out(tup.clone()) {
+                return Err((e, iter::once(tup).chain(tuples).collect()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back the tuples that a failed `collect` did not move.
+    fn put_back(&mut self, tuples: Vec<Tuple>) {
+        if let Err((e, lost)) = self.out_all(tuples) {
+            error!("lost {} tuples that a failed collect took: {e}", lost.len());
+        }
+    }
+
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
+        match self.deliver(tup)? {
+            Some(tup) => self.store.out(tup),
+            None => Ok(()),
+        }
+    }
+
+    /// Hands the tuple over to pending matches. Returns the tuple if no pending `tuple_in`
+    /// consumed it, so that it can be put into the store.
+    fn deliver(&self, tup: Tuple) -> Result<Option<Tuple>, Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
+        }
+        let mut pending = lock(&self.pending);
+        pending.visit_matching(&tup, |waiter| {
+            if let Waiter::Notify(tx) = waiter {
+                if tx.unbounded_send(tup.clone()).is_err() {
+                    trace!("tuple_out: subscriber is gone");
+                }
+            }
+        });
+        let readers = pending.take_all_if(&tup, |waiter| matches!(waiter, Waiter::Read(_)));
+        for waiter in readers {
+            if let Waiter::Read(tx) = waiter {
+                if tx.send(tup.clone()).is_err() {
+                    trace!("tuple_out: reader is gone");
+                }
             }
         }
+        let mut tup = tup;
+        while let Some(waiter) =
+            pending.take_first_if(&tup, |waiter| matches!(waiter, Waiter::Take(_)))
+        {
+            if let Waiter::Take(tx) = waiter {
+                match tx.send(tup) {
+                    Ok(()) => return Ok(None),
+                    Err(returned) => {
+                        trace!("tuple_out: taker is gone, trying the next one");
+                        tup = returned;
+                    }
+                }
+            }
+        }
+        Ok(Some(tup))
+    }
+}
+
+/// A local transaction on a space.
+///
+/// Tuples put in by the transaction are buffered and only become visible to others on
+/// `commit`. Tuples taken by the transaction are hidden from the transaction but only removed
+/// from the store on `commit`, so that `abort` leaves them as they were, with their place in the
+/// retrieval order and their leases. Dropping a transaction without committing it aborts it.
+/// Transactions never wait for a matching tuple.
+pub struct Transaction<'a, T: Store> {
+    space:   &'a mut Space<T>,
+    taken:   Vec<Tuple>,
+    written: Vec<Tuple>,
+    done:    bool,
+}
+
+impl<T> Transaction<'_, T>
+where
+    T: Store,
+{
+    /// Find a matching tuple, retrieve AND remove it from the space if there is one.
+    /// Tuples put in by this transaction are matched first.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_in(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        if let Some(index) = self.written.iter().position(|t| tup.matches(t)) {
+            return Ok(Some(self.written.remove(index)));
+        }
+        let taken = self.untaken(tup)?;
+        self.taken.extend(taken.clone());
+        Ok(taken)
+    }
+
+    /// Find a matching tuple, retrieve but NOT remove it from the space if there is one.
+    /// Tuples put in by this transaction are matched first.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_rd(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        if let Some(t) = self.written.iter().find(|t| tup.matches(t)) {
+            return Ok(Some(t.clone()));
+        }
+        self.untaken(tup)
+    }
+
+    /// Returns the first stored tuple matching tup of which the transaction has not taken all
+    /// copies yet.
+    fn untaken(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        match self.space.store.rdp(tup)? {
+            Some(found) if self.taken.iter().any(|t| t.is_identical(&found)) => {}
+            found => return Ok(found),
+        }
+        let mut hidden = self.taken.clone();
+        Ok(self.space.store.rd_all(tup)?.into_iter().find(|found| {
+            // skips as many copies of a tuple as the transaction has taken
+            let index = hidden.iter().position(|t| t.is_identical(found));
+            index.map(|index| hidden.swap_remove(index)).is_none()
+        }))
+    }
+
+    /// Put a tuple into the space once the transaction is committed.
+    ///
+    /// # Errors
+    /// If the tuple is undefined.
+    pub fn tuple_out(&mut self, tup: Tuple) -> Result<(), Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
+        }
+        self.written.push(tup);
+        Ok(())
+    }
+
+    /// Removes the tuples taken by the transaction from the store and makes the tuples put in
+    /// by it visible, handing them over to pending matches as with `Space::tuple_out`.
+    ///
+    /// Pending matches are only served once all changes have been made. If the store fails a
+    /// change, the changes made so far are undone: the tuples put in are taken out again, and
+    /// the taken ones are put back, without their lease and as if they were put in anew. A
+    /// `PersistentStore` logs every change of a commit, including the ones that undo it, on its
+    /// own, so a crash in the middle of a commit leaves the changes logged up to then.
+    ///
+    /// # Errors
+    /// If a tuple cannot be taken out of or put into the store.
+    pub fn commit(mut self) -> Result<(), Error> {
+        trace!("commit");
+        self.done = true;
+        let taken = mem::take(&mut self.taken);
+        let written = mem::take(&mut self.written);
+        let store = &mut self.space.store;
+        for (i, tup) in taken.iter().enumerate() {
+            if let Err(e) = store.inp(tup) {
+                undo(store, &taken[..i], &[]);
+                return Err(e);
+            }
+        }
+        for (i, tup) in written.iter().enumerate() {
+            if let Err(e) = store.out(tup.clone()) {
+                undo(store, &taken, &written[..i]);
+                return Err(e);
+            }
+        }
+        for tup in written {
+            if self.space.deliver(tup.clone())?.is_none() {
+                self.space.store.inp(&tup)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Discards the tuples put in by the transaction and leaves the ones it has taken in the
+    /// store.
+    pub fn abort(mut self) {
+        self.rollback();
+    }
+
+    fn rollback(&mut self) {
+        trace!("rollback");
+        self.done = true;
+        self.written.clear();
+        self.taken.clear();
+    }
+}
+
+impl<T> Drop for Transaction<'_, T>
+where
+    T: Store,
+{
+    fn drop(&mut self) {
+        if !self.done {
+            self.rollback();
+        }
+    }
+}
+
+/// Undoes the changes of a failed commit: takes the tuples put in out of the store again and
+/// puts the taken ones back. Failures are only logged, as the commit has failed already.
+fn undo<T: Store>(store: &mut T, taken: &[Tuple], written: &[Tuple]) {
+    for tup in written {
+        if let Err(e) = store.inp(tup) {
+            error!("cannot take {tup} out again after a failed commit: {e}");
+        }
+    }
+    for tup in taken {
+        if let Err(e) = store.out(tup.clone()) {
+            error!("cannot put {tup} back after a failed commit: {e}");
+        }
+    }
+}
+
+/// Removes a waiter from the wildcard tree, unless the space is gone already.
+fn unregister(pending: &Weak<Mutex<wildcard::Tree<Waiter>>>, tup: &Tuple, key: u64) {
+    if let Some(pending) = pending.upgrade() {
+        lock(&pending).remove(tup, key);
+    }
+}
+
+/// Recovers from a poisoned lock instead of propagating another thread's panic.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A cloneable handle to a space that can be shared between threads.
+///
+/// The space is only locked for as long as it takes to start an operation. A blocking `tuple_in`
+/// or `tuple_rd` releases the lock before its `Match` is awaited, so other threads can still
+/// `tuple_out` the tuple it is waiting for.
+pub struct SharedSpace<T: Store> {
+    space: Arc<Mutex<Space<T>>>,
+}
+
+impl<T: Store> Clone for SharedSpace<T> {
+    fn clone(&self) -> Self {
+        SharedSpace {
+            space: Arc::clone(&self.space),
+        }
+    }
+}
+
+impl<T> SharedSpace<T>
+where
+    T: Store,
+{
+    pub fn new(store: T) -> SharedSpace<T> {
+        SharedSpace {
+            space: Arc::new(Mutex::new(Space::new(store))),
+        }
+    }
+
+    /// Find a matching tuple, retrieve AND remove it from the space.
+    /// Use `futures::executor::block_on` on the returned match to block the current thread.
+    #[must_use]
+    pub fn tuple_in(&self, tup: Tuple) -> Match {
+        self.lock().tuple_in(tup)
+    }
+
+    /// Find a matching tuple, retrieve but NOT remove it from the space.
+    /// Use `futures::executor::block_on` on the returned match to block the current thread.
+    #[must_use]
+    pub fn tuple_rd(&self, tup: Tuple) -> Match {
+        self.lock().tuple_rd(tup)
+    }
+
+    /// Linda `inp`: find a matching tuple, retrieve AND remove it from the space if there is one.
+    /// Never waits, returns `None` if there is no match.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_in(&self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.lock().try_in(tup)
+    }
+
+    /// Linda `rdp`: find a matching tuple, retrieve but NOT remove it from the space if there is
+    /// one. Never waits, returns `None` if there is no match.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn try_rd(&self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.lock().try_rd(tup)
+    }
+
+    /// Like `tuple_in`, but gives up waiting for a matching tuple after `timeout`.
+    #[must_use]
+    pub fn tuple_in_timeout(&self, tup: Tuple, timeout: Duration) -> Timeout {
+        self.lock().tuple_in_timeout(tup, timeout)
+    }
+
+    /// Like `tuple_rd`, but gives up waiting for a matching tuple after `timeout`.
+    #[must_use]
+    pub fn tuple_rd_timeout(&self, tup: Tuple, timeout: Duration) -> Timeout {
+        self.lock().tuple_rd_timeout(tup, timeout)
+    }
+
+    /// Subscribe to all tuples matching `tup` that are put into the space from now on.
+    ///
+    /// # Errors
+    /// If the subscription cannot be registered in the wildcard tree.
+    pub fn subscribe(&self, tup: Tuple) -> Result<Subscription, Error> {
+        self.lock().subscribe(tup)
+    }
+
+    /// The order in which the store of the space hands out matching tuples.
+    #[must_use]
+    pub fn policy(&self) -> Policy {
+        self.lock().policy()
+    }
+
+    /// Find all matching tuples, retrieve AND remove them from the space at once.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn tuple_in_all(&self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.lock().tuple_in_all(tup)
+    }
+
+    /// Find all matching tuples, retrieve but NOT remove them from the space.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn tuple_rd_all(&self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.lock().tuple_rd_all(tup)
+    }
+
+    /// Linda `collect`: moves all tuples matching `tup` from this space into `dst` and returns
+    /// how many were moved. Both spaces are locked one after the other, never at the same time.
+    /// If a tuple cannot be put into `dst`, the ones that have not been moved yet are put back.
+    ///
+    /// # Errors
+    /// If the tuples cannot be taken from this space or put into `dst`.
+    pub fn collect<U: Store>(&self, dst: &SharedSpace<U>, tup: &Tuple) -> Result<usize, Error> {
+        let tuples = self.tuple_in_all(tup)?;
+        let count = tuples.len();
+        let moved = dst.lock().out_all(tuples);
+        if let Err((e, unmoved)) = moved {
+            self.lock().put_back(unmoved);
+            return Err(e);
+        }
+        Ok(count)
+    }
+
+    /// Linda `copy-collect`: copies all tuples matching `tup` from this space into `dst` and
+    /// returns how many were copied. Both spaces are locked one after the other, never at the
+    /// same time.
+    ///
+    /// # Errors
+    /// If the tuples cannot be read from this space or put into `dst`.
+    pub fn copy_collect<U: Store>(
+        &self,
+        dst: &SharedSpace<U>,
+        tup: &Tuple,
+    ) -> Result<usize, Error> {
+        let tuples = self.tuple_rd_all(tup)?;
+        let count = tuples.len();
+        let mut dst = dst.lock();
+        tuples.into_iter().try_for_each(|t| dst.out(t))?;
+        Ok(count)
+    }
+
+    /// Inserts a tuple into the space, handing it over to a waiting match if there is one.
+    #[must_use]
+    pub fn tuple_out(&self, tup: Tuple) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        self.lock().tuple_out(tup)
+    }
+
+    /// Runs `f` in a transaction while holding the lock on the space. The transaction is
+    /// committed if `f` returns `Ok` and aborted if it returns an error or panics.
+    ///
+    /// # Errors
+    /// The error returned by `f`, or if committing the transaction fails.
+    pub fn transaction<F, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Transaction<'_, T>) -> Result<R, E>,
+        E: From<Error>,
+    {
+        let mut space = self.lock();
+        let mut txn = space.transaction();
+        let result = match f(&mut txn) {
+            Ok(result) => txn.commit().map(|()| result).map_err(E::from),
+            Err(e) => {
+                txn.abort();
+                Err(e)
+            }
+        };
+        drop(space);
+        result
+    }
+
+    /// Like `tuple_out`, but the tuple expires after `ttl`. Returns a lease on the tuple, or
+    /// `None` if a pending `tuple_in` consumed it right away.
+    ///
+    /// # Errors
+    /// If the tuple is undefined or cannot be put into the store.
+    pub fn tuple_out_with_ttl(&self, tup: Tuple, ttl: Duration) -> Result<Option<Lease>, Error> {
+        self.lock().tuple_out_with_ttl(tup, ttl)
+    }
+
+    /// Let the leased tuple expire `ttl` from now. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn renew(&self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        self.lock().renew(lease, ttl)
+    }
+
+    /// Remove the leased tuple from the space. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn cancel(&self, lease: &Lease) -> Result<bool, Error> {
+        self.lock().cancel(lease)
+    }
+
+    /// Reclaim the memory of all expired tuples and return how many there were.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    pub fn purge_expired(&self) -> Result<usize, Error> {
+        self.lock().purge_expired()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Space<T>> {
+        lock(&self.space)
+    }
+}
+
+impl<T> SharedSpace<T>
+where
+    T: Store + Send + 'static,
+{
+    /// Linda `eval`: computes the active fields of a tuple concurrently on a worker pool and puts
+    /// the resulting passive tuple into the space once all of them are done.
+    ///
+    /// The returned future resolves as soon as the tuple is in the space. Dropping it does not
+    /// cancel the evaluation.
+    ///
+    /// # Panics
+    /// If the worker pool cannot be started.
+    pub fn eval(&self, fields: Vec<Field>) -> impl Future<Output = Result<(), Error>> {
+        let space = self.clone();
+        let (tx, rx) = oneshot::channel();
+        eval::pool().spawn_ok(async move {
+            let result = match eval::evaluate(fields).await {
+                Ok(tup) => space.tuple_out(tup).await,
+                Err(e) => Err(e),
+            };
+            let _ = tx.send(result);
+        });
+        rx.map(|result| result.unwrap_or(Err(Error::FieldPanicked)))
+    }
+
+    /// Reclaims expired tuples in the background every `interval`, on the same worker pool that
+    /// evaluates active tuples. Stops once the last handle to the space is dropped.
+    ///
+    /// # Panics
+    /// If the worker pool cannot be started.
+    pub fn purge_expired_every(&self, interval: Duration) {
+        let space = Arc::downgrade(&self.space);
+        eval::pool().spawn_ok(async move {
+            loop {
+                Delay::new(interval).await;
+                let Some(space) = space.upgrade() else {
+                    break;
+                };
+                let purged = lock(&space).purge_expired();
+                match purged {
+                    Ok(purged) => trace!("purged {purged} expired tuples"),
+                    Err(e) => warn!("unable to purge expired tuples: {e}"),
+                }
+            }
+        });
     }
 }
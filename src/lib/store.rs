@@ -3,105 +3,639 @@
 //! A Store is an associative memory which stores and retrieves tuples.
 //! Any data structure that implements the store trait can be used for storing tuples.
 
-use std::collections::BTreeSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, Bound, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
 
-use crate::tuple::Tuple;
+use rand::seq::{IteratorRandom, SliceRandom};
 
-#[derive(Debug, Clone)]
-pub struct InsertUndefinedTuple;
+use crate::error::Error;
+use crate::tuple::{Tuple, E};
 
-impl std::error::Error for InsertUndefinedTuple {}
+/// The order in which a store hands out the tuples matching a template.
+///
+/// `inp` and `rdp` return the first match in this order, `in_all` and `rd_all` return all
+/// matches in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// The tuple that was put in first.
+    Fifo,
+    /// The tuple that was put in last.
+    Lifo,
+    /// The smallest tuple, ordered like `Ord for Tuple`.
+    Ordered,
+    /// Any of the matching tuples, picked at random.
+    Random,
+}
+
+/// A lease on a tuple that has been put into a store with a time to live.
+///
+/// Once the lease expires the tuple becomes invisible and is eventually reclaimed. The lease
+/// can be renewed or cancelled for as long as it has not expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    tup: Tuple,
+    id:  u64,
+}
 
-impl std::fmt::Display for InsertUndefinedTuple {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "attempted to insert an undefined tuple into the space")
+impl Lease {
+    #[must_use]
+    pub const fn new(tup: Tuple, id: u64) -> Lease {
+        Lease { tup, id }
+    }
+
+    /// The leased tuple.
+    #[must_use]
+    pub const fn tuple(&self) -> &Tuple {
+        &self.tup
+    }
+
+    /// The store specific id of the lease.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
     }
 }
 
+/// When a lease expires, or `None` if that is too far off to be represented, in which case the
+/// lease never expires.
+type Expiry = Option<Instant>;
+
+/// Returns the expiry of a lease for `ttl` from `now`.
+fn expiry(now: Instant, ttl: Duration) -> Expiry {
+    now.checked_add(ttl)
+}
+
+/// Returns true if the lease has expired by `now`.
+fn has_passed(expiry: Expiry, now: Instant) -> bool {
+    expiry.is_some_and(|expiry| expiry <= now)
+}
+
 /// A Store is an associative memory which stores and retrieves tuples.
 /// Implementors should only store _defined_ tuples.
 pub trait Store {
-    /// Read a matching tuple and remove it atomically.rust idiomatic error handlingrust idiomatic error handling
-    fn inp(&mut self, tup: &Tuple) -> Option<Tuple>;
+    /// Read a matching tuple and remove it atomically.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error>;
 
     /// Read a matching tuple.
-    fn rdp(&mut self, tup: &Tuple) -> Option<Tuple>;
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error>;
+
+    /// Read all matching tuples and remove them atomically.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error>;
+
+    /// Read all matching tuples.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error>;
 
     /// Write a tuple.
     ///
     /// # Errors
-    /// `InsertUndefinedTuple` in the attempt of inserting an undefined tuple into the space.
-    fn out(&mut self, tup: Tuple) -> std::result::Result<(), InsertUndefinedTuple>;
+    /// `Error::UndefinedTuple` in the attempt of inserting an undefined tuple into the space.
+    fn out(&mut self, tup: Tuple) -> Result<(), Error>;
+
+    /// Write a tuple that expires after `ttl` and return a lease on it.
+    ///
+    /// # Errors
+    /// `Error::UndefinedTuple` in the attempt of inserting an undefined tuple into the space.
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error>;
+
+    /// Let the leased tuple expire `ttl` from now. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error>;
+
+    /// Remove the leased tuple right away. Returns false if the tuple is gone already.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error>;
+
+    /// Remove all expired tuples and return how many were removed.
+    ///
+    /// # Errors
+    /// If the store cannot access its storage.
+    fn purge_expired(&mut self) -> Result<usize, Error>;
+
+    /// The order in which matching tuples are handed out.
+    fn policy(&self) -> Policy;
 }
 
 /// A simple, naive in-memory implementation of a Store.
-#[derive(Default)]
+///
+/// Every tuple that is put in is stored as a separate copy, numbered in the order in which the
+/// copies came in. Copies put in with a time to live are tracked along with their expiry, and
+/// expired copies are skipped by all reads until `purge_expired` removes them. Matches are handed
+/// out in `Policy::Ordered` unless another policy is chosen with `with_policy`.
 #[allow(clippy::module_name_repetitions)]
-pub struct SimpleStore(BTreeSet<Tuple>);
+pub struct SimpleStore {
+    tuples:   BTreeSet<(Tuple, u64)>,
+    leases:   BTreeMap<u64, (Tuple, Expiry)>,
+    inserted: u64,
+    policy:   Policy,
+}
+
+impl Default for SimpleStore {
+    fn default() -> Self {
+        SimpleStore::new()
+    }
+}
 
 impl SimpleStore {
     #[must_use]
     pub const fn new() -> Self {
-        SimpleStore(BTreeSet::new())
+        SimpleStore::with_policy(Policy::Ordered)
+    }
+
+    /// Creates a store that hands out matches in the order of the policy.
+    #[must_use]
+    pub const fn with_policy(policy: Policy) -> Self {
+        SimpleStore {
+            tuples: BTreeSet::new(),
+            leases: BTreeMap::new(),
+            inserted: 0,
+            policy,
+        }
     }
 
+    /// The number of stored copies, including expired ones that have not been purged yet.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.tuples.len()
     }
 
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.0.len() == 0
+        self.tuples.len() == 0
+    }
+
+    /// Stores a new copy of the tuple and returns its number.
+    fn insert(&mut self, tup: Tuple) -> u64 {
+        self.inserted += 1;
+        self.tuples.insert((tup, self.inserted));
+        self.inserted
+    }
+
+    /// Returns true if the stored copy has a lease that ran out.
+    fn is_expired(&self, copy: u64, now: Instant) -> bool {
+        self.leases
+            .get(&copy)
+            .is_some_and(|(_, expiry)| has_passed(*expiry, now))
+    }
+
+    /// Returns true if the lease is on a copy that has not expired yet.
+    fn is_current(&self, lease: &Lease, now: Instant) -> bool {
+        self.leases
+            .get(&lease.id)
+            .is_some_and(|(tup, expiry)| tup.is_identical(&lease.tup) && !has_passed(*expiry, now))
+    }
+
+    /// Returns the stored copies within the range of the template, the copies of a tuple oldest
+    /// first. Constraints with bounds that contradict each other make for an empty range.
+    fn candidates<'a>(&'a self, tup: &Tuple) -> impl Iterator<Item = &'a (Tuple, u64)> {
+        let (lo, hi) = if tup.is_defined() {
+            (Bound::Included(tup.clone()), Bound::Included(tup.clone()))
+        } else {
+            tup.range()
+        };
+        let empty = match (&lo, &hi) {
+            (Bound::Excluded(lo), Bound::Excluded(hi)) => lo >= hi,
+            (
+                Bound::Included(lo) | Bound::Excluded(lo),
+                Bound::Included(hi) | Bound::Excluded(hi),
+            ) => lo > hi,
+            _ => false,
+        };
+        // the copies of a tuple are numbered from 1 to u64::MAX
+        let lo = match lo {
+            Bound::Included(lo) => Bound::Included((lo, 0)),
+            Bound::Excluded(lo) => Bound::Excluded((lo, u64::MAX)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let hi = match hi {
+            Bound::Included(hi) => Bound::Included((hi, u64::MAX)),
+            Bound::Excluded(hi) => Bound::Excluded((hi, 0)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        (!empty)
+            .then(|| self.tuples.range((lo, hi)))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Returns the live copies matching the template.
+    fn matching<'a>(
+        &'a self,
+        tup: &'a Tuple,
+        now: Instant,
+    ) -> impl Iterator<Item = &'a (Tuple, u64)> {
+        self.candidates(tup)
+            .filter(move |(m, copy)| tup.matches(m) && !self.is_expired(*copy, now))
+    }
+
+    /// Returns the live copy matching the template that comes first in the policy.
+    fn select(&self, tup: &Tuple) -> Option<(Tuple, u64)> {
+        let mut found = self.matching(tup, Instant::now());
+        let copy = match self.policy {
+            Policy::Fifo => found.min_by_key(|(_, copy)| *copy),
+            Policy::Lifo => found.max_by_key(|(_, copy)| *copy),
+            Policy::Ordered => found.next(),
+            Policy::Random => found.choose(&mut rand::rng()),
+        };
+        copy.cloned()
+    }
+
+    /// Returns all live copies matching the template in the order of the policy.
+    fn arranged(&self, tup: &Tuple) -> Vec<(Tuple, u64)> {
+        let mut found = self
+            .matching(tup, Instant::now())
+            .cloned()
+            .collect::<Vec<_>>();
+        match self.policy {
+            Policy::Fifo => found.sort_unstable_by_key(|(_, copy)| *copy),
+            Policy::Lifo => found.sort_unstable_by_key(|(_, copy)| Reverse(*copy)),
+            Policy::Ordered => {}
+            Policy::Random => found.shuffle(&mut rand::rng()),
+        }
+        found
+    }
+
+    /// Removes the copy together with its lease.
+    fn remove(&mut self, copy: &(Tuple, u64)) -> Option<Tuple> {
+        self.leases.remove(&copy.1);
+        self.tuples.take(copy).map(|(tup, _)| tup)
     }
 }
 
 /// Implements the store trait for `SimpleStore`.
 impl Store for SimpleStore {
-    /// Insert the tuple into the space if it is defined.
-    fn out(&mut self, tup: Tuple) -> std::result::Result<(), InsertUndefinedTuple> {
+    /// Insert a copy of the tuple into the space if it is defined.
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
         if !tup.is_defined() {
-            // TODO: Replace with custom error
-            return Err(InsertUndefinedTuple);
+            return Err(Error::UndefinedTuple);
         }
-        self.0.insert(tup);
+        self.insert(tup);
         Ok(())
     }
 
-    /// Returns a copy of the tuple if it is defined and in the space.
-    /// Otherwise look for any tuple that matches tup and return a copy.
-    /// If no matches can be found, return `None`.
-    fn rdp(&mut self, tup: &Tuple) -> Option<Tuple> {
-        if tup.is_defined() && self.0.contains(tup) {
-            return Some(tup.clone());
+    /// Insert a copy of the tuple into the space if it is defined and lease it for `ttl`.
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
         }
-        for m in self.0.range(tup.range()) {
-            if tup.matches(m) {
-                return Some(m.clone());
-            }
+        let copy = self.insert(tup.clone());
+        self.leases
+            .insert(copy, (tup.clone(), expiry(Instant::now(), ttl)));
+        Ok(Lease::new(tup, copy))
+    }
+
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        let now = Instant::now();
+        if !self.is_current(lease, now) {
+            return Ok(false);
+        }
+        self.leases
+            .insert(lease.id, (lease.tup.clone(), expiry(now, ttl)));
+        Ok(true)
+    }
+
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        if !self.is_current(lease, Instant::now()) {
+            return Ok(false);
+        }
+        Ok(self.remove(&(lease.tup.clone(), lease.id)).is_some())
+    }
+
+    fn purge_expired(&mut self) -> Result<usize, Error> {
+        let now = Instant::now();
+        let expired = self
+            .leases
+            .iter()
+            .filter(|(_, (_, expiry))| has_passed(*expiry, now))
+            .map(|(copy, (tup, _))| (tup.clone(), *copy))
+            .collect::<Vec<_>>();
+        for copy in &expired {
+            self.remove(copy);
+        }
+        Ok(expired.len())
+    }
+
+    /// Returns a copy of the tuple matching tup that comes first in the policy, or `None` if
+    /// there is none.
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        Ok(self.select(tup).map(|(m, _)| m))
+    }
+
+    /// Takes one copy of the tuple matching tup that comes first in the policy out of the space
+    /// and returns it, or `None` if there is none.
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        Ok(self.select(tup).and_then(|copy| self.remove(&copy)))
+    }
+
+    /// Takes all copies of the tuples matching tup out of the space and returns them in the order
+    /// of the policy.
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        let found = self.arranged(tup);
+        for copy in &found {
+            self.remove(copy);
+        }
+        Ok(found.into_iter().map(|(m, _)| m).collect())
+    }
+
+    /// Returns all copies of the tuples matching tup in the order of the policy.
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        Ok(self.arranged(tup).into_iter().map(|(m, _)| m).collect())
+    }
+
+    fn policy(&self) -> Policy {
+        self.policy
+    }
+}
+
+/// The key of a per-position index: the arity of the tuple, the position of the field and its
+/// value.
+type Field = (usize, usize, Key<E>);
+
+/// A key of the indexes of `IndexedStore` and `PersistentStore`. Keys are equal if their values
+/// are identical, so that a tuple holding a NaN finds its own index entries again.
+#[derive(Debug, Hash)]
+pub(crate) struct Key<T>(pub(crate) T);
+
+impl PartialEq for Key<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_identical(&other.0)
+    }
+}
+
+impl PartialEq for Key<Tuple> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.is_identical(&other.0)
+    }
+}
+
+impl<T> Eq for Key<T> where Key<T>: PartialEq {}
+
+/// An in-memory store that indexes tuples by arity and by the value of each of their fields.
+///
+/// A template is matched against the tuples of the most selective index it can use: those
+/// sharing the value of the defined field that is the rarest one, or all tuples of its arity if
+/// it has no defined fields. Typed wildcards and constraints are not indexed. Only tuples of the
+/// same arity as the template match. Every tuple that is put in is stored as a separate copy.
+/// Matches are handed out in `Policy::Fifo` unless another policy is chosen with `with_policy`.
+#[allow(clippy::module_name_repetitions)]
+pub struct IndexedStore {
+    tuples:   HashMap<u64, Tuple>,
+    ids:      HashMap<Key<Tuple>, BTreeSet<u64>>,
+    arities:  HashMap<usize, BTreeSet<u64>>,
+    fields:   HashMap<Field, BTreeSet<u64>>,
+    leases:   HashMap<u64, Expiry>,
+    inserted: u64,
+    policy:   Policy,
+}
+
+impl Default for IndexedStore {
+    fn default() -> Self {
+        IndexedStore::new()
+    }
+}
+
+impl IndexedStore {
+    #[must_use]
+    pub fn new() -> Self {
+        IndexedStore::with_policy(Policy::Fifo)
+    }
+
+    /// Creates a store that hands out matches in the order of the policy.
+    #[must_use]
+    pub fn with_policy(policy: Policy) -> Self {
+        IndexedStore {
+            tuples: HashMap::new(),
+            ids: HashMap::new(),
+            arities: HashMap::new(),
+            fields: HashMap::new(),
+            leases: HashMap::new(),
+            inserted: 0,
+            policy,
+        }
+    }
+
+    /// The number of stored copies, including expired ones that have not been purged yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tuples.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tuples.is_empty()
+    }
+
+    /// Stores and indexes a new copy of the tuple and returns its id.
+    fn insert(&mut self, tup: Tuple) -> u64 {
+        self.inserted += 1;
+        let id = self.inserted;
+        let arity = tup.len();
+        self.arities.entry(arity).or_default().insert(id);
+        for (position, e) in tup.iter().enumerate() {
+            self.fields
+                .entry((arity, position, Key(e.clone())))
+                .or_default()
+                .insert(id);
         }
-        None
+        self.ids.entry(Key(tup.clone())).or_default().insert(id);
+        self.tuples.insert(id, tup);
+        id
     }
 
-    /// Returns a tuple and take it out of the space if it is defined and in the space.
-    /// Otherwise look for any tuple that matches tup, take it out of the space and return it.
-    /// /// If no matches can be found, return an empty tuple.
-    fn inp(&mut self, tup: &Tuple) -> Option<Tuple> {
-        if tup.is_defined() {
-            return self.0.take(tup);
+    /// Removes the tuple together with its lease and its index entries.
+    fn remove(&mut self, id: u64) -> Option<Tuple> {
+        let tup = Key(self.tuples.remove(&id)?);
+        unindex(&mut self.ids, &tup, id);
+        let Key(tup) = tup;
+        self.leases.remove(&id);
+        let arity = tup.len();
+        unindex(&mut self.arities, &arity, id);
+        for (position, e) in tup.iter().enumerate() {
+            unindex(&mut self.fields, &(arity, position, Key(e.clone())), id);
         }
-        let mut result = None;
-        for m in self.0.range(tup.range()) {
-            println!("check whether {tup} matches {m}");
-            if tup.matches(m) {
-                result = Some(m.clone());
-                break;
+        Some(tup)
+    }
+
+    /// Returns true if the stored copy has a lease that ran out.
+    fn is_expired(&self, id: u64, now: Instant) -> bool {
+        self.leases
+            .get(&id)
+            .is_some_and(|expiry| has_passed(*expiry, now))
+    }
+
+    /// Returns true if the lease is on a copy that has not expired yet.
+    fn is_current(&self, lease: &Lease, now: Instant) -> bool {
+        self.leases
+            .get(&lease.id)
+            .is_some_and(|expiry| !has_passed(*expiry, now))
+            && self
+                .tuples
+                .get(&lease.id)
+                .is_some_and(|tup| tup.is_identical(&lease.tup))
+    }
+
+    /// Returns the ids of the most selective index for the template, or `None` if a defined field
+    /// of the template is not stored in any tuple of its arity.
+    fn candidates(&self, tup: &Tuple) -> Option<&BTreeSet<u64>> {
+        let arity = tup.len();
+        let mut best = self.arities.get(&arity)?;
+        for (position, e) in tup.iter().enumerate().filter(|(_, e)| e.is_defined()) {
+            let ids = self.fields.get(&(arity, position, Key(e.clone())))?;
+            if ids.len() < best.len() {
+                best = ids;
             }
         }
-        println!("result: {result:?}");
-        if let Some(ref m) = result {
-            return self.0.take(m);
+        Some(best)
+    }
+
+    /// Returns the ids of the live copies matching the template, oldest first.
+    fn matching<'a>(
+        &'a self,
+        tup: &'a Tuple,
+        now: Instant,
+    ) -> impl DoubleEndedIterator<Item = u64> + 'a {
+        let ids = if tup.is_defined() {
+            self.ids.get(&Key(tup.clone()))
+        } else {
+            self.candidates(tup)
+        };
+        ids.into_iter()
+            .flatten()
+            .copied()
+            .filter(move |id| tup.matches(&self.tuples[id]) && !self.is_expired(*id, now))
+    }
+
+    /// Returns the id of the live copy matching the template that comes first in the policy.
+    fn select(&self, tup: &Tuple) -> Option<u64> {
+        let mut found = self.matching(tup, Instant::now());
+        match self.policy {
+            Policy::Fifo => found.next(),
+            Policy::Lifo => found.next_back(),
+            Policy::Ordered => found.min_by_key(|id| (&self.tuples[id], *id)),
+            Policy::Random => found.choose(&mut rand::rng()),
         }
-        None
+    }
+
+    /// Returns the ids of all live copies matching the template in the order of the policy.
+    fn arranged(&self, tup: &Tuple) -> Vec<u64> {
+        let mut found = self.matching(tup, Instant::now()).collect::<Vec<_>>();
+        match self.policy {
+            Policy::Fifo => {}
+            Policy::Lifo => found.reverse(),
+            Policy::Ordered => found.sort_by_key(|id| &self.tuples[id]),
+            Policy::Random => found.shuffle(&mut rand::rng()),
+        }
+        found
+    }
+}
+
+/// Removes the id from the index entry under the key, and the entry if it is left empty.
+fn unindex<K: Eq + Hash>(index: &mut HashMap<K, BTreeSet<u64>>, key: &K, id: u64) {
+    if let Some(ids) = index.get_mut(key) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// Implements the store trait for `IndexedStore`.
+impl Store for IndexedStore {
+    /// Insert a copy of the tuple into the space if it is defined.
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
+        }
+        self.insert(tup);
+        Ok(())
+    }
+
+    /// Insert a copy of the tuple into the space if it is defined and lease it for `ttl`.
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error> {
+        if !tup.is_defined() {
+            return Err(Error::UndefinedTuple);
+        }
+        let id = self.insert(tup.clone());
+        self.leases.insert(id, expiry(Instant::now(), ttl));
+        Ok(Lease::new(tup, id))
+    }
+
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        let now = Instant::now();
+        if !self.is_current(lease, now) {
+            return Ok(false);
+        }
+        self.leases.insert(lease.id, expiry(now, ttl));
+        Ok(true)
+    }
+
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        if !self.is_current(lease, Instant::now()) {
+            return Ok(false);
+        }
+        Ok(self.remove(lease.id).is_some())
+    }
+
+    fn purge_expired(&mut self) -> Result<usize, Error> {
+        let now = Instant::now();
+        let expired = self
+            .leases
+            .iter()
+            .filter(|(_, expiry)| has_passed(**expiry, now))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in &expired {
+            self.remove(*id);
+        }
+        Ok(expired.len())
+    }
+
+    /// Returns a copy of the tuple matching tup that comes first in the policy, or `None` if
+    /// there is none.
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        Ok(self.select(tup).map(|id| self.tuples[&id].clone()))
+    }
+
+    /// Takes the tuple matching tup that comes first in the policy out of the space and returns
+    /// it, or `None` if there is none.
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        Ok(self.select(tup).and_then(|id| self.remove(id)))
+    }
+
+    /// Takes all tuples matching tup out of the space and returns them in the order of the
+    /// policy.
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        let found = self.arranged(tup);
+        Ok(found.iter().filter_map(|id| self.remove(*id)).collect())
+    }
+
+    /// Returns copies of all tuples matching tup in the order of the policy.
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        Ok(self
+            .arranged(tup)
+            .iter()
+            .map(|id| self.tuples[id].clone())
+            .collect())
+    }
+
+    fn policy(&self) -> Policy {
+        self.policy
     }
 }
@@ -5,38 +5,156 @@
 use std::cmp::Ordering;
 use std::collections::Bound;
 use std::fmt::{Display, Formatter, Result};
-use std::iter::Iterator;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, Iterator};
+use std::ops::Index;
+use std::str::FromStr;
+
+use crate::constraint::Constraint;
+
+/// Type represents the data type of a tuple element, used by typed wildcards.
+///
+/// Types are ordered the same way as the values of each type are ordered among each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Type {
+    Int,
+    Long,
+    Unsigned,
+    Float,
+    Bool,
+    Str,
+    Bytes,
+    Tuple,
+}
+
+impl Type {
+    /// Returns the type whose values are ordered right after the values of this type.
+    #[must_use]
+    pub const fn next(self) -> Option<Type> {
+        match self {
+            Type::Int => Some(Type::Long),
+            Type::Long => Some(Type::Unsigned),
+            Type::Unsigned => Some(Type::Float),
+            Type::Float => Some(Type::Bool),
+            Type::Bool => Some(Type::Str),
+            Type::Str => Some(Type::Bytes),
+            Type::Bytes => Some(Type::Tuple),
+            Type::Tuple => None,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Type::Int => "int",
+                Type::Long => "i64",
+                Type::Unsigned => "u64",
+                Type::Float => "float",
+                Type::Bool => "bool",
+                Type::Str => "str",
+                Type::Bytes => "bytes",
+                Type::Tuple => "tuple",
+            }
+        )
+    }
+}
+
+impl FromStr for Type {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Type, ()> {
+        match s {
+            "int" => Ok(Type::Int),
+            "i64" => Ok(Type::Long),
+            "u64" => Ok(Type::Unsigned),
+            "float" => Ok(Type::Float),
+            "bool" => Ok(Type::Bool),
+            "str" => Ok(Type::Str),
+            "bytes" => Ok(Type::Bytes),
+            "tuple" => Ok(Type::Tuple),
+            _ => Err(()),
+        }
+    }
+}
 
 /// E represents a tuple element.
+///
+/// With the `serde` feature, elements are serialized as a map from the type name to the value,
+/// like `{"int": 42}`, and wildcards as plain strings `"any"` and `"none"`.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum E {
     /// Integer data type.
     ///
     /// Implemented as 32-bit integer (i32).
+    #[cfg_attr(feature = "serde", serde(rename = "int"))]
     I(i32),
+    /// Long integer data type.
+    ///
+    /// Implemented as 64-bit integer (i64).
+    #[cfg_attr(feature = "serde", serde(rename = "long"))]
+    L(i64),
+    /// Unsigned integer data type.
+    ///
+    /// Implemented as 64-bit unsigned integer (u64).
+    #[cfg_attr(feature = "serde", serde(rename = "unsigned"))]
+    U(u64),
     /// Floating point data type.
     ///
     /// Implemented as double precision (f64).
+    #[cfg_attr(feature = "serde", serde(rename = "float"))]
     D(f64),
+    /// Boolean data type.
+    ///
+    /// Implemented as bool.
+    #[cfg_attr(feature = "serde", serde(rename = "bool"))]
+    B(bool),
     /// String data type.
     ///
     /// Implemented as String.
+    #[cfg_attr(feature = "serde", serde(rename = "str"))]
     S(String),
+    /// Raw bytes data type.
+    ///
+    /// Implemented as vector of bytes (Vec<u8>).
+    #[cfg_attr(feature = "serde", serde(rename = "bytes"))]
+    R(Vec<u8>),
     /// Tuple data type.
     ///
     /// Implemented as vector of tuple types (Vec<E>).
+    #[cfg_attr(feature = "serde", serde(rename = "tuple"))]
     T(Tuple),
     /// Any data type.
     ///
     /// In context of this tuple, Any stands for the wild card that is used for pattern matching
     /// when querying the tuple space for certain tuples, and marks the beginning of a matching
     /// range when searching for matching tuples.
+    #[cfg_attr(feature = "serde", serde(rename = "any"))]
     Any,
+    /// Typed wild card.
+    ///
+    /// Like Any, but only matches values of the given type. Marks the beginning of the range of
+    /// values of that type when searching for matching tuples.
+    #[cfg_attr(feature = "serde", serde(rename = "any_of"))]
+    AnyOf(Type),
+    /// Constrained wild card.
+    ///
+    /// Matches all values satisfying the constraint, e.g. all integers within a range. Sorts
+    /// right before the lowest value it can match.
+    #[cfg_attr(feature = "serde", serde(rename = "where"))]
+    Where(Box<Constraint>),
     /// None data type.
     ///
     /// In context of this tuple, None represents "no match" when searching, and marks the end of a
     /// matching range when searching for matching tuples. All defined values will fall between
     /// Any..None.
+    #[cfg_attr(feature = "serde", serde(rename = "none"))]
     None,
 }
 
@@ -62,11 +180,39 @@ impl Ord for E {
             (&E::None, &E::None) => Ordering::Equal,
             (&E::None, _) => Ordering::Greater,
             (_, &E::None) => Ordering::Less,
-            (E::I(a), E::I(b)) => {
-                a.cmp(b)
+            (E::Where(a), E::Where(b)) => {
+                let ((a_key, a_tie), (b_key, b_tie)) = (a.position(), b.position());
+                a_key
+                    .cmp(&b_key)
+                    .then(a_tie.cmp(&b_tie))
+                    .then_with(|| a.to_string().cmp(&b.to_string()))
+            }
+            (E::Where(a), b) => {
+                let (key, tie) = a.position();
+                key.cmp(b).then(tie)
+            }
+            (a, E::Where(b)) => {
+                let (key, tie) = b.position();
+                a.cmp(&key).then(tie.reverse())
+            }
+            (E::AnyOf(a), E::AnyOf(b)) => a.cmp(b),
+            (E::AnyOf(a), b) => match b.type_of() {
+                Some(b) if *a > b => Ordering::Greater,
+                _ => Ordering::Less,
+            },
+            (a, E::AnyOf(b)) => match a.type_of() {
+                Some(a) if a < *b => Ordering::Less,
+                _ => Ordering::Greater,
             },
+            (E::I(a), E::I(b)) => a.cmp(b),
             (&E::I(_), _) => Ordering::Less,
             (_, &E::I(_)) => Ordering::Greater,
+            (E::L(a), E::L(b)) => a.cmp(b),
+            (&E::L(_), _) => Ordering::Less,
+            (_, &E::L(_)) => Ordering::Greater,
+            (E::U(a), E::U(b)) => a.cmp(b),
+            (&E::U(_), _) => Ordering::Less,
+            (_, &E::U(_)) => Ordering::Greater,
             (E::D(a), E::D(b)) => {
                 if a < b {
                     Ordering::Less
@@ -78,14 +224,44 @@ impl Ord for E {
             }
             (&E::D(_), _) => Ordering::Less,
             (_, &E::D(_)) => Ordering::Greater,
+            (E::B(a), E::B(b)) => a.cmp(b),
+            (&E::B(_), _) => Ordering::Less,
+            (_, &E::B(_)) => Ordering::Greater,
             (E::S(a), E::S(b)) => a.cmp(b),
             (&E::S(_), _) => Ordering::Less,
             (_, &E::S(_)) => Ordering::Greater,
+            (E::R(a), E::R(b)) => a.cmp(b),
+            (&E::R(_), _) => Ordering::Less,
+            (_, &E::R(_)) => Ordering::Greater,
             (E::T(a), E::T(b)) => a.cmp(b),
         }
     }
 }
 
+impl Hash for E {
+    /// Equal elements hash alike. Wildcards hash by their variant only, as they are not meant to
+    /// be looked up by hash.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            E::I(i) => i.hash(state),
+            E::L(l) => l.hash(state),
+            E::U(u) => u.hash(state),
+            // 0.0 and -0.0 are equal
+            E::D(d) => (if *d == 0.0 { 0.0 } else { *d }).to_bits().hash(state),
+            E::B(b) => b.hash(state),
+            E::S(s) => s.hash(state),
+            E::R(r) => r.hash(state),
+            E::T(t) => t.hash(state),
+            E::AnyOf(t) => t.hash(state),
+            E::Any | E::Where(_) | E::None => {}
+        }
+    }
+}
+
+/// Elements are displayed in the canonical text format that `lexing` parses back into the same
+/// element. Floats always carry a decimal point or are one of `inf`, `-inf` and `nan`, so they
+/// can be told apart from integers. NaN payloads are not preserved.
 impl Display for E {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
@@ -93,10 +269,18 @@ impl Display for E {
             "{}",
             match self {
                 E::I(ref i) => i.to_string(),
+                E::L(ref l) => format!("{l}i64"),
+                E::U(ref u) => format!("{u}u64"),
+                E::D(ref d) if d.is_nan() => "nan".to_string(),
+                E::D(ref d) if d.is_finite() && d.fract() == 0.0 => format!("{d}.0"),
                 E::D(ref d) => d.to_string(),
-                E::S(ref s) => s.to_string(),
+                E::B(ref b) => b.to_string(),
+                E::S(ref s) => format!("\"{}\"", s.escape_debug()),
+                E::R(ref r) => format!("b\"{}\"", r.escape_ascii()),
                 E::T(ref t) => t.to_string(),
                 E::Any => "_".to_string(),
+                E::AnyOf(ref t) => format!("?{t}"),
+                E::Where(ref c) => c.to_string(),
                 E::None => "nil".to_string(),
             }
         )
@@ -108,31 +292,70 @@ impl E {
         E::S(s.into())
     }
 
+    /// Returns the type of a value, or `None` for wildcards and `E::None`.
+    #[must_use]
+    pub const fn type_of(&self) -> Option<Type> {
+        match self {
+            E::I(_) => Some(Type::Int),
+            E::L(_) => Some(Type::Long),
+            E::U(_) => Some(Type::Unsigned),
+            E::D(_) => Some(Type::Float),
+            E::B(_) => Some(Type::Bool),
+            E::S(_) => Some(Type::Str),
+            E::R(_) => Some(Type::Bytes),
+            E::T(_) => Some(Type::Tuple),
+            E::Any | E::AnyOf(_) | E::Where(_) | E::None => None,
+        }
+    }
+
     /// Returns false if one or more elements are the wildcard `E::Any`, recursively.
     #[must_use]
     pub fn is_defined(&self) -> bool {
         #[allow(clippy::match_same_arms)]
         match self {
             E::I(_) => true,
+            E::L(_) => true,
+            E::U(_) => true,
             E::D(_) => true,
+            E::B(_) => true,
             E::S(_) => true,
+            E::R(_) => true,
             E::Any => false,
+            E::AnyOf(_) => false,
+            E::Where(_) => false,
             E::None => false,
             E::T(ref t) => t.is_defined(),
         }
     }
 
+    /// Returns true if both elements are the same value. Unlike `==`, floats are compared by
+    /// their bits as `matches` does, so that a NaN is identical to itself.
+    pub(crate) fn is_identical(&self, other: &E) -> bool {
+        match (self, other) {
+            (E::D(a), E::D(b)) => a.to_bits() == b.to_bits(),
+            (E::T(a), E::T(b)) => a.is_identical(b),
+            (a, b) => a == b,
+        }
+    }
+
     /// Returns true if the other tuple matches this one. Tuples match when elements in each
     /// respective position are equal, or one or both of them in a given position is the wildcard
-    /// `E::Any`.
+    /// `E::Any`, a typed wildcard `E::AnyOf` of the other element's type, or a constraint
+    /// `E::Where` the other element satisfies.
     #[must_use]
     pub fn matches(&self, other: &E) -> bool {
         #[allow(clippy::match_same_arms)]
         match (self, other) {
             (E::I(a), E::I(b)) => a == b,
+            (E::L(a), E::L(b)) => a == b,
+            (E::U(a), E::U(b)) => a == b,
             (E::D(a), E::D(b)) => a.to_bits() == b.to_bits(),
+            (E::B(a), E::B(b)) => a == b,
             (E::S(a), E::S(b)) => a == b,
+            (E::R(a), E::R(b)) => a == b,
             (E::T(a), E::T(b)) => a.matches(b),
+            (E::AnyOf(t), e) | (e, E::AnyOf(t)) => e.type_of() == Some(*t),
+            (E::Where(c), e) | (e, E::Where(c)) => c.matches(e),
             (&E::Any, &E::Any) => false,
             (&E::Any, &E::None) => false,
             (&E::Any, _) => true,
@@ -143,7 +366,8 @@ impl E {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tuple(Vec<E>);
 
 impl Display for Tuple {
@@ -169,17 +393,30 @@ impl Tuple {
 
     /// Creates a new tuple from a given vector of elements.
     #[must_use]
-    pub fn from_vec(v: Vec<E>) -> Tuple {
+    pub const fn from_vec(v: Vec<E>) -> Tuple {
         Tuple(v)
     }
 
+    /// Takes the elements out of the tuple.
+    #[must_use]
+    pub fn into_vec(self) -> Vec<E> {
+        self.0
+    }
+
     /// Returns a reference to the first element of the tuple.
+    ///
+    /// # Panics
+    /// If the tuple is empty, use `get(0)` instead if it might be.
     #[must_use]
     pub fn first(&self) -> &E {
         &self.0[0]
     }
 
     /// Returns a tuple of all but the first element of the original tuple.
+    /// Allocates a new tuple, use `as_slice()` to look at the elements without copying them.
+    ///
+    /// # Panics
+    /// If the tuple is empty.
     #[must_use]
     pub fn rest(&self) -> Tuple {
         Tuple::new(&self.0[1..])
@@ -187,10 +424,45 @@ impl Tuple {
 
     /// Returns true if the tuple is empty, false otherwise.
     #[must_use]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
 
+    /// Returns the number of elements of the tuple.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the number of elements of the tuple, i.e. its arity in Linda terms.
+    #[must_use]
+    pub const fn arity(&self) -> usize {
+        self.len()
+    }
+
+    /// Returns a reference to the element at the given position, or `None` if the tuple is
+    /// shorter than that.
+    #[must_use]
+    pub fn get(&self, i: usize) -> Option<&E> {
+        self.0.get(i)
+    }
+
+    /// Returns an iterator over the elements of the tuple.
+    pub fn iter(&self) -> std::slice::Iter<'_, E> {
+        self.0.iter()
+    }
+
+    /// Returns a view of the elements of the tuple. Slicing it further does not allocate.
+    #[must_use]
+    pub fn as_slice(&self) -> &[E] {
+        &self.0
+    }
+
+    /// Appends an element to the end of the tuple.
+    pub fn push(&mut self, e: E) {
+        self.0.push(e);
+    }
+
     /// Returns true if all elements are defined, i.e: none of them are wildcards.
     /// Returns false if the tuple contains any wildcards.
     #[must_use]
@@ -198,10 +470,19 @@ impl Tuple {
         self.0.iter().all(E::is_defined)
     }
 
-    /// Returns true if this tuple matches the other.
+    /// Returns true if both tuples hold identical elements, see `E::is_identical`.
+    pub(crate) fn is_identical(&self, other: &Tuple) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(a, b)| a.is_identical(b))
+    }
+
+    /// Returns true if this tuple matches the other. Only tuples of the same arity match.
     #[must_use]
     pub fn matches(&self, other: &Tuple) -> bool {
-        (self.is_empty() == other.is_empty())
+        self.len() == other.len()
             && self
                 .0
                 .iter()
@@ -228,6 +509,8 @@ impl Tuple {
                 .iter()
                 .map(|x| match x {
                     &E::Any => E::None,
+                    E::AnyOf(t) => t.next().map_or(E::None, E::AnyOf),
+                    E::Where(c) => c.terminator(),
                     E::T(t) => E::T(t.terminator()),
                     e => e.clone(),
                 })
@@ -236,6 +519,38 @@ impl Tuple {
     }
 }
 
+impl Index<usize> for Tuple {
+    type Output = E;
+
+    fn index(&self, i: usize) -> &E {
+        &self.0[i]
+    }
+}
+
+impl IntoIterator for Tuple {
+    type IntoIter = std::vec::IntoIter<E>;
+    type Item = E;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Tuple {
+    type IntoIter = std::slice::Iter<'a, E>;
+    type Item = &'a E;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<E> for Tuple {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Tuple {
+        Tuple(iter.into_iter().collect())
+    }
+}
+
 #[macro_export]
 macro_rules! tuple {
     ($($x:expr),*) => (
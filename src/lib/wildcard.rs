@@ -1,21 +1,38 @@
-extern crate indextree_ng;
-use indextree_ng::{Arena, NodeId};
+use std::collections::VecDeque;
 
-use crate::error::{Error, ResultExt};
+use crate::error::Error;
 use crate::tuple::{Tuple, E};
 
-pub enum Node<T> {
-    Root,
-    Path(E),
-    Leaf(Option<T>),
+/// A node of the wildcard tree.
+///
+/// Paths branch off on the next element of a tuple. Items that reached the end of their tuple
+/// path are queued up in the node together with their insertion number.
+struct Node<T> {
+    paths: Vec<(E, Node<T>)>,
+    items: VecDeque<(u64, T)>,
 }
 
-/// An arena-based wildcard tree that can insert and take values
-/// associated with a pending wildcard.
+impl<T> Node<T> {
+    const fn new() -> Node<T> {
+        Node {
+            paths: Vec::new(),
+            items: VecDeque::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.items.is_empty()
+    }
+}
+
+/// A wildcard tree that can insert and take values associated with a pending wildcard.
 /// Used by Space for coordination.
+///
+/// Several values can wait for the same pattern. Matching values are always taken out in the
+/// order in which they were inserted, regardless of the path they are waiting on.
 pub struct Tree<T> {
-    arena:   Arena<Node<T>>,
-    root_id: NodeId,
+    root:     Node<T>,
+    inserted: u64,
 }
 
 impl<T> Default for Tree<T> {
@@ -25,95 +42,166 @@ impl<T> Default for Tree<T> {
 }
 
 impl<T> Tree<T> {
-    pub fn new() -> Tree<T> {
-        let mut arena = Arena::new();
-        let root_id = arena.new_node(Node::Root);
-        Tree { arena, root_id }
+    #[must_use]
+    pub const fn new() -> Tree<T> {
+        Tree {
+            root:     Node::new(),
+            inserted: 0,
+        }
+    }
+
+    /// Returns true if no values are waiting in the tree.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
     }
 
     /// Public interface for inserting an item into the wildcard tree,
-    /// along a tuple 'path'.
+    /// along a tuple 'path'. The item is queued up behind any items inserted earlier along the
+    /// same path. Returns a key with which the item can be removed again.
     /// # Errors
-    /// `IndexTreeError` if inserting into the wildcard tree fails
-    pub fn insert(&mut self, tup: Tuple, item: T) -> Result<(), Error> {
-        debug!("insert {:?}", tup);
-        let id = self.root_id;
-        self.do_insert(id, tup, item)
+    /// Currently inserting into the wildcard tree does not fail.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn insert(&mut self, tup: Tuple, item: T) -> Result<u64, Error> {
+        debug!("insert {tup:?}");
+        self.inserted += 1;
+        Self::do_insert(&mut self.root, tup.as_slice(), (self.inserted, item));
+        Ok(self.inserted)
     }
 
-    #[allow(clippy::needless_pass_by_value)]
-    fn do_insert(&mut self, id: NodeId, tup: Tuple, item: T) -> Result<(), Error> {
-        trace!("do_insert {:?} {:?}", id, tup);
-        // If we have an empty tuple, insert the item in a new leaf node of NodeId.
-        if tup.is_empty() {
-            let child_id = self.arena.new_node(Node::Leaf(Some(item)));
-            // TODO: More expressive error description
-            id.append(child_id, &mut self.arena)
-                .chain_err(|| "insert failed")?;
-            trace!("do_insert appending {:?} child of {:?}", child_id, id);
-            return Ok(());
-        }
-        // If the tuple is not empty, look for a child node whose id matches
-        // the first element of the tuple. If we can't find it, we create a new child node.
-        let next = id
-            .children(&self.arena)
-            .find(|child_id| match self.arena[*child_id].data {
-                Node::Path(ref e) => e == tup.first(),
-                _ => false,
+    fn do_insert(node: &mut Node<T>, tup: &[E], item: (u64, T)) {
+        trace!("do_insert {tup:?}");
+        // If we have an empty tuple, queue the item up at this node.
+        let Some((first, rest)) = tup.split_first() else {
+            node.items.push_back(item);
+            return;
+        };
+        // If the tuple is not empty, look for a path whose element is the first element of the
+        // tuple. If we can't find it, we create a new path.
+        let index = node
+            .paths
+            .iter()
+            .position(|(e, _)| e.is_identical(first))
+            .unwrap_or_else(|| {
+                node.paths.push((first.clone(), Node::new()));
+                node.paths.len() - 1
             });
         // Finally continue inserting with the rest of the tuple.
-        if let Some(id) = next {
-            self.do_insert(id, tup.rest(), item)
-        } else {
-            let child_id = self.arena.new_node(Node::Path(tup.first().clone()));
-            id.append(child_id, &mut self.arena)
-                .chain_err(|| "insert failed")?;
-            trace!("do_insert appending {:?} child of {:?}", child_id, id);
-            self.do_insert(child_id, tup.rest(), item)
-        }
+        Self::do_insert(&mut node.paths[index].1, rest, item);
     }
 
     /// Public interface for retrieving an item out of the wildcard tree,
-    /// from a tuple 'path'.
+    /// from a tuple 'path'. Returns the oldest item along any matching path.
+    #[allow(clippy::needless_pass_by_value)]
     pub fn take(&mut self, tup: Tuple) -> Option<T> {
-        debug!("take {:?}", tup);
-        let id = self.root_id;
-        self.do_take(id, tup)
+        debug!("take {tup:?}");
+        self.take_first_if(&tup, |_| true)
     }
 
-    #[allow(clippy::needless_pass_by_value)]
-    fn do_take(&mut self, id: NodeId, tup: Tuple) -> Option<T> {
-        trace!("take {:?} {:?}", id, tup);
-        if tup.is_empty() {
-            let Some(child_id) = id
-                .children(&self.arena)
-                .find(|child_id| matches!(self.arena[*child_id].data, Node::Leaf(_))) else { return None };
-            let result = match self.arena[child_id].data {
-                Node::Leaf(ref mut item) => item.take(),
-                _ => return None,
-            };
-            trace!("take matched, removing node and returning");
-            child_id.detach(&mut self.arena);
-            self.arena.remove_node(child_id);
-            return result;
+    /// Retrieves the oldest item along a path matching the tuple for which `pred` returns true.
+    pub fn take_first_if<F>(&mut self, tup: &Tuple, mut pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut oldest = None;
+        Self::do_find(&self.root, tup.as_slice(), &mut |(n, item)| {
+            if oldest.is_none_or(|o| *n < o) && pred(item) {
+                oldest = Some(*n);
+            }
+        });
+        let oldest = oldest?;
+        Self::do_take(&mut self.root, tup.as_slice(), &mut |(n, _)| *n == oldest)
+            .pop()
+            .map(|(_, item)| item)
+    }
+
+    /// Retrieves all items along paths matching the tuple for which `pred` returns true, oldest
+    /// first. Items for which `pred` returns false stay in the tree.
+    pub fn take_all_if<F>(&mut self, tup: &Tuple, mut pred: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut taken = Self::do_take(&mut self.root, tup.as_slice(), &mut |(_, item)| pred(item));
+        taken.sort_unstable_by_key(|(n, _)| *n);
+        taken.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Visits all items along paths matching the tuple, oldest first, without taking them out.
+    pub fn visit_matching<F>(&self, tup: &Tuple, mut visit: F)
+    where
+        F: FnMut(&T),
+    {
+        let mut found = Vec::new();
+        Self::do_find(&self.root, tup.as_slice(), &mut |item| found.push(item));
+        found.sort_unstable_by_key(|(n, _)| *n);
+        for (_, item) in found {
+            visit(item);
         }
-        let children = id
-            .children(&self.arena)
-            .filter(|child_id| {
-                let node = &self.arena[*child_id].data;
-                trace!("take: child {:?}", child_id);
-                match node {
-                    Node::Path(ref e) => e.matches(tup.first()),
-                    _ => false,
-                }
-            })
-            .collect::<Vec<NodeId>>();
-        trace!("take: potential matches: {:?}", children);
-        for child_id in children {
-            if let Some(item) = self.do_take(child_id, tup.rest()) {
-                return Some(item);
+    }
+
+    /// Removes the item with the given key, which has been inserted along the exact same tuple
+    /// path. Returns `None` if the item has already been taken out of the tree.
+    pub fn remove(&mut self, tup: &Tuple, key: u64) -> Option<T> {
+        debug!("remove {tup:?}");
+        Self::do_remove(&mut self.root, tup.as_slice(), key)
+    }
+
+    fn do_remove(node: &mut Node<T>, tup: &[E], key: u64) -> Option<T> {
+        let Some((first, rest)) = tup.split_first() else {
+            let index = node.items.iter().position(|(n, _)| *n == key)?;
+            return node.items.remove(index).map(|(_, item)| item);
+        };
+        let index = node.paths.iter().position(|(e, _)| e.is_identical(first))?;
+        let item = Self::do_remove(&mut node.paths[index].1, rest, key);
+        if node.paths[index].1.is_empty() {
+            node.paths.remove(index);
+        }
+        item
+    }
+
+    /// Visits all items along paths matching the tuple.
+    fn do_find<'a, F>(node: &'a Node<T>, tup: &[E], visit: &mut F)
+    where
+        F: FnMut(&'a (u64, T)),
+    {
+        let Some((first, rest)) = tup.split_first() else {
+            node.items.iter().for_each(visit);
+            return;
+        };
+        for (e, child) in &node.paths {
+            if e.matches(first) {
+                Self::do_find(child, rest, visit);
             }
         }
-        None
+    }
+
+    /// Removes all items along paths matching the tuple for which `pred` returns true and
+    /// prunes paths left empty.
+    fn do_take<F>(node: &mut Node<T>, tup: &[E], pred: &mut F) -> Vec<(u64, T)>
+    where
+        F: FnMut(&(u64, T)) -> bool,
+    {
+        trace!("take {tup:?}");
+        let mut taken = Vec::new();
+        let Some((first, rest)) = tup.split_first() else {
+            let mut kept = VecDeque::with_capacity(node.items.len());
+            for item in node.items.drain(..) {
+                if pred(&item) {
+                    taken.push(item);
+                } else {
+                    kept.push_back(item);
+                }
+            }
+            node.items = kept;
+            return taken;
+        };
+        node.paths.retain_mut(|(e, child)| {
+            if e.matches(first) {
+                trace!("take: potential match {e:?}");
+                taken.append(&mut Self::do_take(child, rest, pred));
+            }
+            !child.is_empty()
+        });
+        taken
     }
 }
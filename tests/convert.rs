@@ -0,0 +1,156 @@
+#[macro_use]
+extern crate rustupolis;
+
+use rustupolis::convert::FromTupleError;
+use rustupolis::tuple::{Tuple, Type, E};
+use rustupolis::{FromTuple, IntoTuple};
+
+#[derive(Debug, PartialEq, IntoTuple, FromTuple)]
+struct Task {
+    name:     String,
+    id:       i64,
+    priority: i32,
+    done:     bool,
+}
+
+#[derive(Debug, PartialEq, IntoTuple, FromTuple)]
+struct Job(String, Task, Vec<u8>);
+
+#[derive(Debug, PartialEq, IntoTuple, FromTuple)]
+struct Ping;
+
+#[test]
+fn test_elements() {
+    assert_eq!(E::from(42), E::I(42));
+    assert_eq!(E::from(42i64), E::L(42));
+    assert_eq!(E::from(42u64), E::U(42));
+    assert_eq!(E::from(4.2), E::D(4.2));
+    assert_eq!(E::from(true), E::B(true));
+    assert_eq!(E::from("foo"), E::str("foo"));
+    assert_eq!(E::from(String::from("foo")), E::str("foo"));
+    assert_eq!(E::from(&b"foo"[..]), E::R(b"foo".to_vec()));
+    assert_eq!(E::from(tuple![]), E::T(tuple![]));
+
+    assert_eq!(i32::try_from(E::I(42)), Ok(42));
+    assert_eq!(String::try_from(E::str("foo")), Ok(String::from("foo")));
+    assert_eq!(
+        i32::try_from(E::L(42)),
+        Err(FromTupleError::Type {
+            position: 0,
+            expected: Type::Int,
+            found:    E::L(42),
+        })
+    );
+}
+
+#[test]
+fn test_rust_tuples() {
+    let tup = Tuple::from(("task", 42, 0.5));
+    assert_eq!(tup, tuple![E::str("task"), E::I(42), E::D(0.5)]);
+    assert_eq!(
+        <(String, i32, f64)>::try_from(tup.clone()),
+        Ok((String::from("task"), 42, 0.5))
+    );
+    assert_eq!(
+        <(String, i32)>::try_from(tup.clone()),
+        Err(FromTupleError::Arity {
+            expected: 2,
+            found:    3,
+        })
+    );
+    let err = <(String, bool, f64)>::try_from(tup).unwrap_err();
+    assert_eq!(
+        err,
+        FromTupleError::Type {
+            position: 1,
+            expected: Type::Bool,
+            found:    E::I(42),
+        }
+    );
+    assert_eq!(err.to_string(), "expected bool at position 1, found 42");
+}
+
+#[test]
+fn test_derive() {
+    let task = Task {
+        name:     String::from("build"),
+        id:       7,
+        priority: 5,
+        done:     false,
+    };
+    let tup = tuple![E::str("build"), E::L(7), E::I(5), E::B(false)];
+    assert_eq!(Tuple::from(task), tup);
+    assert_eq!(
+        Task::try_from(tup),
+        Ok(Task {
+            name:     String::from("build"),
+            id:       7,
+            priority: 5,
+            done:     false,
+        })
+    );
+    assert_eq!(
+        Task::try_from(tuple![E::str("build"), E::I(7), E::I(5), E::B(false)]),
+        Err(FromTupleError::Type {
+            position: 1,
+            expected: Type::Long,
+            found:    E::I(7),
+        })
+    );
+
+    // Structs nest as tuples.
+    let job = Job(
+        String::from("nightly"),
+        Task {
+            name:     String::from("test"),
+            id:       8,
+            priority: 1,
+            done:     true,
+        },
+        vec![1, 2],
+    );
+    let tup = Tuple::from(job);
+    assert_eq!(
+        tup,
+        tuple![
+            E::str("nightly"),
+            E::T(tuple![E::str("test"), E::L(8), E::I(1), E::B(true)]),
+            E::R(vec![1, 2])
+        ]
+    );
+    assert_eq!(Job::try_from(tup).map(|job| job.1.id), Ok(8));
+    assert_eq!(
+        Job::try_from(tuple![E::str("nightly"), E::str("test"), E::R(vec![])]),
+        Err(FromTupleError::Type {
+            position: 1,
+            expected: Type::Tuple,
+            found:    E::str("test"),
+        })
+    );
+    // Errors within a nested tuple keep their position in it.
+    let err = Job::try_from(tuple![
+        E::str("nightly"),
+        E::T(tuple![E::str("test"), E::L(8), E::I(1), E::I(1)]),
+        E::R(vec![])
+    ])
+    .unwrap_err();
+    assert_eq!(
+        err,
+        FromTupleError::Nested {
+            position: 1,
+            error:    Box::new(FromTupleError::Type {
+                position: 3,
+                expected: Type::Bool,
+                found:    E::I(1),
+            }),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "expected bool at position 3, found 1 in the tuple at position 1"
+    );
+
+    assert_eq!(Tuple::from(Ping), tuple![]);
+    assert_eq!(Ping::try_from(tuple![]), Ok(Ping));
+    assert!(Ping::try_from(tuple![E::I(0)]).is_err());
+}
@@ -0,0 +1,312 @@
+#[macro_use]
+extern crate rustupolis;
+
+use proptest::prelude::*;
+use rustupolis::constraint::Constraint;
+use rustupolis::error::Error;
+use rustupolis::store::{IndexedStore, Policy, SimpleStore, Store};
+use rustupolis::tuple::{Tuple, Type, E};
+use std::collections::Bound;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_out() {
+    let mut is = IndexedStore::new();
+    assert!(is.is_empty());
+    for i in 0..42 {
+        is.out(tuple![E::I(i)]).unwrap();
+    }
+    is.out(tuple![E::I(0)]).unwrap();
+    assert_eq!(is.len(), 43);
+    assert!(matches!(
+        is.out(tuple![E::I(1), E::Any]),
+        Err(Error::UndefinedTuple)
+    ));
+}
+
+#[test]
+fn test_duplicates() {
+    let mut is = IndexedStore::new();
+    for _ in 0..10 {
+        is.out(tuple![E::str("token")]).unwrap();
+    }
+    is.out(tuple![E::str("other")]).unwrap();
+    assert_eq!(is.len(), 11);
+    assert_eq!(is.rd_all(&tuple![E::str("token")]).unwrap().len(), 10);
+    for taken in 1..=10 {
+        assert_eq!(
+            is.inp(&tuple![E::str("token")]).unwrap(),
+            Some(tuple![E::str("token")])
+        );
+        assert_eq!(is.len(), 11 - taken);
+    }
+    assert_eq!(is.inp(&tuple![E::str("token")]).unwrap(), None);
+
+    // every copy has a lease of its own
+    let lease = is
+        .out_with_ttl(tuple![E::str("other")], Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(is.in_all(&tuple![E::Any]).unwrap().len(), 2);
+    assert!(!is.cancel(&lease).unwrap());
+}
+
+#[test]
+fn test_selective_fields() {
+    let mut is = IndexedStore::new();
+    for i in 0..1000 {
+        let kind = if i % 100 == 0 { "job" } else { "log" };
+        is.out(tuple![E::I(i), E::str(kind), E::I(i % 7)]).unwrap();
+    }
+    assert_eq!(
+        is.rd_all(&tuple![E::Any, E::str("job"), E::I(2)]).unwrap(),
+        vec![
+            tuple![E::I(100), E::str("job"), E::I(2)],
+            tuple![E::I(800), E::str("job"), E::I(2)]
+        ]
+    );
+    assert_eq!(
+        is.inp(&tuple![E::Any, E::str("job"), E::I(2)]).unwrap(),
+        Some(tuple![E::I(100), E::str("job"), E::I(2)])
+    );
+    assert_eq!(is.rdp(&tuple![E::I(100), E::Any, E::Any]).unwrap(), None);
+    assert_eq!(
+        is.rdp(&tuple![E::I(5), E::str("log"), E::I(5)]).unwrap(),
+        Some(tuple![E::I(5), E::str("log"), E::I(5)])
+    );
+    assert_eq!(
+        is.rdp(&tuple![E::Any, E::str("task"), E::Any]).unwrap(),
+        None
+    );
+    assert_eq!(
+        is.in_all(&tuple![E::Any, E::str("log"), E::Any])
+            .unwrap()
+            .len(),
+        990
+    );
+    assert_eq!(is.len(), 9);
+}
+
+#[test]
+fn test_arity() {
+    let mut is = IndexedStore::new();
+    is.out(tuple![E::I(1), E::I(2), E::I(3)]).unwrap();
+    is.out(tuple![E::I(1), E::I(2)]).unwrap();
+    is.out(tuple![]).unwrap();
+    assert_eq!(
+        is.rd_all(&tuple![E::I(1), E::Any]).unwrap(),
+        vec![tuple![E::I(1), E::I(2)]]
+    );
+    assert_eq!(is.rd_all(&tuple![E::Any]).unwrap(), vec![]);
+    assert_eq!(is.inp(&tuple![]).unwrap(), Some(tuple![]));
+    assert_eq!(is.len(), 2);
+}
+
+#[test]
+fn test_oldest_first() {
+    let mut is = IndexedStore::new();
+    for job in ["c", "a", "b"] {
+        is.out(tuple![E::str("job"), E::str(job)]).unwrap();
+    }
+    let template = tuple![E::str("job"), E::Any];
+    assert_eq!(
+        is.rdp(&template).unwrap(),
+        Some(tuple![E::str("job"), E::str("c")])
+    );
+    assert_eq!(
+        is.in_all(&template).unwrap(),
+        vec![
+            tuple![E::str("job"), E::str("c")],
+            tuple![E::str("job"), E::str("a")],
+            tuple![E::str("job"), E::str("b")]
+        ]
+    );
+    assert!(is.is_empty());
+}
+
+#[test]
+fn test_ttl() {
+    let mut is = IndexedStore::new();
+    let short = is
+        .out_with_ttl(tuple![E::I(1)], Duration::from_millis(50))
+        .unwrap();
+    let long = is
+        .out_with_ttl(tuple![E::I(2)], Duration::from_secs(60))
+        .unwrap();
+    is.out(tuple![E::I(3)]).unwrap();
+    assert_eq!(is.rd_all(&tuple![E::Any]).unwrap().len(), 3);
+
+    thread::sleep(Duration::from_millis(100));
+    // expired tuples are invisible but only reclaimed by a purge
+    assert_eq!(is.rdp(&tuple![E::I(1)]).unwrap(), None);
+    assert_eq!(is.inp(&tuple![E::Any]).unwrap(), Some(tuple![E::I(2)]));
+    assert!(!is.renew(&short, Duration::from_secs(60)).unwrap());
+    assert!(!is.renew(&long, Duration::from_secs(60)).unwrap());
+    assert_eq!(is.len(), 2);
+    assert_eq!(is.purge_expired().unwrap(), 1);
+    assert_eq!(is.len(), 1);
+
+    let lease = is
+        .out_with_ttl(tuple![E::I(4)], Duration::from_secs(60))
+        .unwrap();
+    assert!(is.renew(&lease, Duration::from_secs(60)).unwrap());
+    assert!(is.cancel(&lease).unwrap());
+    assert!(!is.cancel(&lease).unwrap());
+    assert_eq!(is.rdp(&tuple![E::I(4)]).unwrap(), None);
+}
+
+#[test]
+fn test_long_ttl() {
+    let mut is = IndexedStore::new();
+    // a lease too long to be represented never expires
+    let lease = is.out_with_ttl(tuple![E::I(1)], Duration::MAX).unwrap();
+    assert!(is.renew(&lease, Duration::from_secs(u64::MAX / 2)).unwrap());
+    assert_eq!(is.purge_expired().unwrap(), 0);
+    assert_eq!(is.rdp(&tuple![E::Any]).unwrap(), Some(tuple![E::I(1)]));
+    assert!(is.cancel(&lease).unwrap());
+    assert!(is.is_empty());
+}
+
+#[test]
+fn test_nan() {
+    let mut is = IndexedStore::new();
+    let nan = tuple![E::str("x"), E::D(f64::NAN)];
+    is.out(nan.clone()).unwrap();
+    is.out(nan.clone()).unwrap();
+    is.out(tuple![E::str("x"), E::D(0.0)]).unwrap();
+    let lease = is
+        .out_with_ttl(nan.clone(), Duration::from_secs(60))
+        .unwrap();
+
+    // a NaN is found by itself, and floats are told apart by their bits
+    assert!(is.rdp(&nan).unwrap().is_some_and(|t| nan.matches(&t)));
+    assert_eq!(is.rd_all(&nan).unwrap().len(), 3);
+    assert_eq!(is.rdp(&tuple![E::str("x"), E::D(-0.0)]).unwrap(), None);
+    assert!(is.renew(&lease, Duration::from_secs(60)).unwrap());
+    assert!(is.cancel(&lease).unwrap());
+    for _ in 0..2 {
+        assert!(is.inp(&nan).unwrap().is_some_and(|t| nan.matches(&t)));
+    }
+    assert_eq!(is.inp(&nan).unwrap(), None);
+    assert_eq!(
+        is.in_all(&tuple![E::Any, E::Any]).unwrap(),
+        vec![tuple![E::str("x"), E::D(0.0)]]
+    );
+    assert!(is.is_empty());
+}
+
+#[test]
+fn test_typed_wildcards_and_constraints() {
+    let mut is = IndexedStore::new();
+    for e in [
+        E::I(1),
+        E::L(2),
+        E::D(3.0),
+        E::str("4"),
+        E::T(tuple![E::I(5)]),
+    ] {
+        is.out(tuple![E::str("key"), e]).unwrap();
+    }
+    assert_eq!(
+        is.rd_all(&tuple![E::str("key"), E::AnyOf(Type::Int)])
+            .unwrap(),
+        vec![tuple![E::str("key"), E::I(1)]]
+    );
+    assert_eq!(
+        is.inp(&tuple![E::AnyOf(Type::Str), E::T(tuple![E::Any])])
+            .unwrap(),
+        Some(tuple![E::str("key"), E::T(tuple![E::I(5)])])
+    );
+    let range = tuple![
+        E::Any,
+        E::Where(Box::new(Constraint::Range(
+            Bound::Included(E::L(0)),
+            Bound::Unbounded
+        )))
+    ];
+    assert_eq!(
+        is.rdp(&range).unwrap(),
+        Some(tuple![E::str("key"), E::L(2)])
+    );
+    // defined templates are looked up directly
+    assert_eq!(
+        is.rdp(&tuple![E::str("key"), E::D(3.0)]).unwrap(),
+        Some(tuple![E::str("key"), E::D(3.0)])
+    );
+}
+
+#[test]
+fn test_policy() {
+    let template = tuple![E::str("job"), E::Any];
+    let job = |name| tuple![E::str("job"), E::str(name)];
+    let jobs = |policy| {
+        let mut is = IndexedStore::with_policy(policy);
+        for name in ["c", "a", "b", "a"] {
+            is.out(job(name)).unwrap();
+        }
+        is
+    };
+    assert_eq!(IndexedStore::new().policy(), Policy::Fifo);
+
+    let mut is = jobs(Policy::Lifo);
+    assert_eq!(is.rdp(&template).unwrap(), Some(job("a")));
+    assert_eq!(is.inp(&template).unwrap(), Some(job("a")));
+    assert_eq!(
+        is.in_all(&template).unwrap(),
+        vec![job("b"), job("a"), job("c")]
+    );
+
+    let mut is = jobs(Policy::Ordered);
+    assert_eq!(is.inp(&template).unwrap(), Some(job("a")));
+    assert_eq!(
+        is.rd_all(&template).unwrap(),
+        vec![job("a"), job("b"), job("c")]
+    );
+
+    let mut is = jobs(Policy::Random);
+    let mut found = is.rd_all(&template).unwrap();
+    found.sort();
+    assert_eq!(found, vec![job("a"), job("a"), job("b"), job("c")]);
+    assert!(is.inp(&template).unwrap().is_some());
+    assert_eq!(is.len(), 3);
+}
+
+fn element() -> impl Strategy<Value = E> {
+    prop_oneof![
+        (0..4).prop_map(E::I),
+        (0..4i64).prop_map(E::L),
+        "[ab]{0,2}".prop_map(E::S),
+    ]
+}
+
+fn template() -> impl Strategy<Value = E> {
+    prop_oneof![
+        element(),
+        Just(E::Any),
+        Just(E::AnyOf(Type::Int)),
+        Just(E::Where(Box::new(Constraint::Prefix("a".to_string())))),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_agrees_with_simplestore(
+        tuples in prop::collection::vec(prop::collection::vec(element(), 1..4), 0..50),
+        templates in prop::collection::vec(prop::collection::vec(template(), 1..4), 1..10),
+    ) {
+        let mut ss = SimpleStore::new();
+        let mut is = IndexedStore::new();
+        for tup in tuples {
+            ss.out(Tuple::from_vec(tup.clone())).unwrap();
+            is.out(Tuple::from_vec(tup)).unwrap();
+        }
+        for template in templates.into_iter().map(Tuple::from_vec) {
+            let mut expected = ss.in_all(&template).unwrap();
+            let mut found = is.in_all(&template).unwrap();
+            expected.sort();
+            found.sort();
+            prop_assert_eq!(found, expected, "{}", template);
+            prop_assert_eq!(is.len(), ss.len());
+        }
+    }
+}
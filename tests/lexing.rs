@@ -1,12 +1,27 @@
 #[macro_use]
 extern crate rustupolis as rt;
 
-use rt::lexing::Lexer;
-use rt::tuple::E;
+use proptest::prelude::*;
+use regex::Regex;
+use rt::constraint::Constraint;
+use rt::lexing::{
+    parse_tuple, parse_tuples, Lexer, ParseError, StreamLexer, TokenKind, TupleReader, MAX_DEPTH,
+};
+use rt::tuple::{Type, E};
+use std::collections::Bound;
+use std::io::{self, Read};
+
+/// Unwraps the parse error of a lexer result, so that results can be compared.
+fn parsed<T>(result: Result<T, rt::Error>) -> Result<T, ParseError> {
+    result.map_err(|e| match e {
+        rt::Error::Parse(e) => e,
+        e => panic!("unexpected error: {e}"),
+    })
+}
 
 #[test]
 fn test_lexer() {
-    let inputs = vec![
+    let inputs = [
         "",
         "(1)",
         "(2.0, 3.15, \"steeze\")",
@@ -14,7 +29,7 @@ fn test_lexer() {
         "(1, 2.0, \"3.15\", _, 666), (\"foo\", \"bar\"), ((1, -2, -3), (-4.1, 5.1, \"6.1\"))",
     ];
 
-    let expected = vec![
+    let expected = [
         vec![],
         vec![rt::tuple!(E::I(1))],
         vec![rt::tuple!(
@@ -53,6 +68,489 @@ fn test_lexer() {
     }
 }
 
+#[test]
+fn test_lexer_wide_types() {
+    check_output(
+        "(42i64, -42i64, 42u64, 42i32, true, false, b\"a\\\"\\\\\\x00\\n\")",
+        &[rt::tuple!(
+            E::L(42),
+            E::L(-42),
+            E::U(42),
+            E::I(42),
+            E::B(true),
+            E::B(false),
+            E::R(b"a\"\\\x00\n".to_vec())
+        )],
+    );
+    // The display syntax can be read back in.
+    let tup = rt::tuple!(
+        E::L(i64::MIN),
+        E::U(u64::MAX),
+        E::B(true),
+        E::R((0..=255).collect())
+    );
+    check_output(&tup.to_string(), &[tup]);
+    // Out of range and malformed values are rejected.
+    for input in [
+        "(-1u64)",
+        "(4294967296)",
+        "(1.5i64)",
+        "(maybe)",
+        "(b\"\\q\")",
+        "(b\"open)",
+    ] {
+        assert_eq!(Lexer::new(input).count(), 0, "{input}");
+    }
+}
+
+#[test]
+fn test_lexer_typed_wildcards() {
+    let tup = rt::tuple!(
+        E::AnyOf(Type::Int),
+        E::AnyOf(Type::Long),
+        E::AnyOf(Type::Unsigned),
+        E::AnyOf(Type::Float),
+        E::AnyOf(Type::Bool),
+        E::AnyOf(Type::Str),
+        E::AnyOf(Type::Bytes),
+        E::AnyOf(Type::Tuple)
+    );
+    check_output(&tup.to_string(), std::slice::from_ref(&tup));
+    check_output(
+        "(?int, ?i64, ?u64, ?float, ?bool, ?str, ?bytes, ?tuple)",
+        &[tup],
+    );
+    assert_eq!(Lexer::new("(?number)").count(), 0);
+    assert_eq!(Lexer::new("(?)").count(), 0);
+}
+
+#[test]
+fn test_lexer_constraints() {
+    let range = |lo, hi| E::Where(Box::new(Constraint::Range(lo, hi)));
+    let tup = rt::tuple!(
+        range(Bound::Excluded(E::I(10)), Bound::Unbounded),
+        range(Bound::Included(E::L(-10)), Bound::Unbounded),
+        range(Bound::Unbounded, Bound::Excluded(E::U(3))),
+        range(Bound::Unbounded, Bound::Included(E::I(3))),
+        range(Bound::Included(E::I(1)), Bound::Excluded(E::I(100))),
+        range(Bound::Included(E::I(-1)), Bound::Included(E::I(1))),
+        range(Bound::Excluded(E::I(1)), Bound::Included(E::I(5))),
+        range(Bound::Unbounded, Bound::Unbounded),
+        E::Where(Box::new(Constraint::Prefix("foo".to_string()))),
+        E::Where(Box::new(Constraint::OneOf(vec![
+            E::str("a"),
+            E::I(1),
+            E::B(true)
+        ])))
+    );
+    check_output(
+        "(>10, >=-10i64, <3u64, <=3, 1..100, -1..=1, >1..=5, .., ^\"foo\", in [\"a\", 1, true])",
+        std::slice::from_ref(&tup),
+    );
+    check_output(
+        "(1.., ..5)",
+        &[rt::tuple!(
+            range(Bound::Included(E::I(1)), Bound::Unbounded),
+            range(Bound::Unbounded, Bound::Excluded(E::I(5)))
+        )],
+    );
+
+    let regex = Lexer::new("(~\"^t.+k$\")").next().unwrap();
+    assert!(regex.matches(&rt::tuple!(E::str("task"))));
+    assert!(!regex.matches(&rt::tuple!(E::str("tasks"))));
+
+    for input in [
+        "(>)",
+        "(<_)",
+        "(.)",
+        "(~\"(\")",
+        "(^foo)",
+        "(in [1, _])",
+        "(in [1",
+    ] {
+        assert_eq!(Lexer::new(input).count(), 0, "{input}");
+    }
+}
+
+#[test]
+fn test_lexer_string_escapes() {
+    check_output(
+        r#"("a\"b", "c\\d", "\t\r\n\0\'", "\u{1F980}\u{e9}", "", "ünï,cödé (ok)")"#,
+        &[rt::tuple!(
+            E::str("a\"b"),
+            E::str("c\\d"),
+            E::str("\t\r\n\0'"),
+            E::str("🦀é"),
+            E::str(""),
+            E::str("ünï,cödé (ok)")
+        )],
+    );
+    // Displayed strings are quoted and escaped, so they can be read back in.
+    let tup = rt::tuple!(
+        E::str("say \"hi\" \\ bye"),
+        E::str("tab\tnew\nline\u{7}\u{200b}"),
+        E::str("日本語 🦀"),
+        E::T(rt::tuple!(E::str(")\","))),
+        E::Where(Box::new(Constraint::Prefix("\"a".to_string()))),
+        E::Where(Box::new(Constraint::OneOf(vec![E::str("x\ny")])))
+    );
+    assert_eq!(parsed(parse_tuple(&tup.to_string())), Ok(tup));
+    let regex = parse_tuple(r#"(~"^\\d+\"$")"#).unwrap();
+    assert!(regex.matches(&rt::tuple!(E::str("42\""))));
+    assert_eq!(parsed(parse_tuple(&regex.to_string())), Ok(regex));
+
+    for (input, offset) in [
+        (r#"("\q")"#, 2),
+        (r#"("ab\u{110000}")"#, 4),
+        (r#"("\u{}")"#, 2),
+        (r#"("\u{1234567}")"#, 2),
+        (r#"("\u1234")"#, 2),
+        (r#"("\u{12")"#, 2),
+    ] {
+        let err = parsed(parse_tuple(input)).unwrap_err();
+        assert_eq!(
+            (err.offset(), err.expected()),
+            (offset, TokenKind::Escape),
+            "{input}"
+        );
+    }
+    let err = parsed(parse_tuple(r#"("abc\"#)).unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (6, TokenKind::Char('"')));
+}
+
+#[test]
+fn test_parse_tuple() {
+    assert_eq!(
+        parsed(parse_tuple("(1, \"foo\")")),
+        Ok(rt::tuple!(E::I(1), E::str("foo")))
+    );
+    assert_eq!(
+        parsed(parse_tuples(" (1) , (2)\n(\"héllo\", \"wörld\")")),
+        Ok(vec![
+            rt::tuple!(E::I(1)),
+            rt::tuple!(E::I(2)),
+            rt::tuple!(E::str("héllo"), E::str("wörld"))
+        ])
+    );
+    assert_eq!(parsed(parse_tuples("")), Ok(vec![]));
+
+    // Errors point at the byte where parsing failed.
+    for (input, offset, expected) in [
+        ("", 0, TokenKind::Tuple),
+        ("(1) (2)", 4, TokenKind::End),
+        ("foo", 0, TokenKind::Tuple),
+        ("(1, \"foo)", 9, TokenKind::Char('"')),
+        ("(1, 2", 5, TokenKind::Char(')')),
+        ("(1, $)", 4, TokenKind::Element),
+        ("(\"é\", $)", 7, TokenKind::Element),
+        ("(4294967296)", 1, TokenKind::Number),
+        ("(?number)", 1, TokenKind::Type),
+        ("(b\"\\q\")", 3, TokenKind::Escape),
+        ("(>_)", 2, TokenKind::Bound),
+        ("(~\"(\")", 1, TokenKind::Regex),
+        ("(in 1)", 4, TokenKind::Char('[')),
+    ] {
+        let err = parsed(parse_tuple(input)).unwrap_err();
+        assert_eq!(
+            (err.offset(), err.expected()),
+            (offset, expected),
+            "{input}"
+        );
+    }
+    let err = parsed(parse_tuples("(1), (2, \"open")).unwrap_err();
+    assert_eq!(err.message(), "unterminated string");
+    assert_eq!(
+        parse_tuple("(1, 2").unwrap_err().to_string(),
+        "unterminated tuple at byte 5, expected `)`"
+    );
+
+    // The iterator stops at the first invalid tuple.
+    let tuples: Vec<rt::Tuple> = Lexer::new("(1) (x) (2)").collect();
+    assert_eq!(tuples, vec![rt::tuple!(E::I(1))]);
+}
+
+#[test]
+fn test_parse_never_panics() {
+    let valid = "(1, -2.5, 3i64, 4u64, true, b\"\\x00\", \"äöü\", _, ?str, >1..=5, ^\"a\", \
+                 ~\"b+\", in [1, 2], ((\"x\")))";
+    // Every prefix of a valid tuple is an error, but never a panic.
+    for (end, _) in valid.char_indices() {
+        let input = &valid[..end];
+        let err = parsed(parse_tuple(input)).unwrap_err();
+        assert!(input.is_char_boundary(err.offset()), "{input}");
+    }
+    assert!(parse_tuple(valid).is_ok());
+
+    // So is random garbage made of the characters of the grammar.
+    let alphabet = valid.chars().chain("$.\\🦀".chars()).collect::<Vec<char>>();
+    let mut seed: u64 = 42;
+    for _ in 0..2000 {
+        let input = (0..32)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                alphabet[(seed >> 33) as usize % alphabet.len()]
+            })
+            .collect::<String>();
+        if let Err(err) = parsed(parse_tuples(&input)) {
+            assert!(input.is_char_boundary(err.offset()), "{input}");
+        }
+    }
+}
+
+#[test]
+fn test_parse_depth() {
+    let nested = |depth| format!("{}{}", "(".repeat(depth + 1), ")".repeat(depth + 1));
+    assert!(parse_tuple(&nested(MAX_DEPTH)).is_ok());
+    let err = parsed(parse_tuple(&nested(MAX_DEPTH + 1))).unwrap_err();
+    assert_eq!(
+        (err.offset(), err.expected(), err.message()),
+        (MAX_DEPTH + 1, TokenKind::Element, "nested too deeply")
+    );
+
+    // Deeply nested input is an error instead of a stack overflow.
+    for input in [
+        "(".repeat(200_000),
+        format!("({}1)", ">".repeat(200_000)),
+        format!("(1{})", "..".repeat(200_000)),
+    ] {
+        let err = parsed(parse_tuple(&input)).unwrap_err();
+        assert_eq!(err.message(), "nested too deeply");
+    }
+    let mut lexer = StreamLexer::new();
+    lexer.feed(nested(200_000).as_bytes());
+    assert!(lexer.next_tuple().unwrap().is_err());
+}
+
+#[test]
+fn test_stream_lexer() {
+    let input = "(1, \"a (quoted) \\\"paren\\\"\", b\"\\\")\"), ((\"日本\"), ~\"[)]\")\n(2.5)";
+    let expected = parse_tuples(input).unwrap();
+    assert_eq!(expected.len(), 3);
+
+    // Tuples come out as soon as they are complete, wherever the input is split.
+    for split in 0..=input.len() {
+        let mut lexer = StreamLexer::new();
+        let mut tuples = Vec::new();
+        lexer.feed(&input.as_bytes()[..split]);
+        tuples.extend(std::iter::from_fn(|| lexer.next_tuple()));
+        lexer.feed(&input.as_bytes()[split..]);
+        tuples.extend(std::iter::from_fn(|| lexer.next_tuple()));
+        assert_eq!(parsed(lexer.finish()), Ok(()));
+        let tuples = parsed(tuples.into_iter().collect::<Result<Vec<_>, _>>());
+        assert_eq!(tuples.as_ref(), Ok(&expected), "split at {split}");
+    }
+
+    let mut lexer = StreamLexer::new();
+    let mut tuples = Vec::new();
+    for b in input.bytes() {
+        lexer.feed(&[b]);
+        tuples.extend(lexer.next_tuple().map(parsed));
+    }
+    assert!(lexer.next_tuple().is_none());
+    assert_eq!(parsed(lexer.finish()), Ok(()));
+    assert_eq!(tuples, expected.into_iter().map(Ok).collect::<Vec<_>>());
+
+    // Finishing scans what has been fed, complete tuples are still handed out afterwards.
+    let mut lexer = StreamLexer::new();
+    lexer.feed(b"(1) (2)\n  ");
+    assert_eq!(parsed(lexer.finish()), Ok(()));
+    lexer.feed(b" \t, ");
+    assert_eq!(parsed(lexer.finish()), Ok(()));
+    let tuples = std::iter::from_fn(|| lexer.next_tuple().map(parsed)).collect::<Vec<_>>();
+    assert_eq!(
+        tuples,
+        vec![Ok(rt::tuple!(E::I(1))), Ok(rt::tuple!(E::I(2)))]
+    );
+    lexer.feed(b"(3) (4");
+    let err = parsed(lexer.finish()).unwrap_err();
+    assert_eq!((err.offset(), err.message()), (20, "unterminated tuple"));
+    assert_eq!(
+        lexer.next_tuple().map(parsed),
+        Some(Ok(rt::tuple!(E::I(3))))
+    );
+    assert!(lexer.next_tuple().is_none());
+}
+
+#[test]
+fn test_stream_lexer_errors() {
+    let mut lexer = StreamLexer::new();
+    lexer.feed(b"(1) (x) $$ (2) (\"\xff\")");
+    let results = std::iter::from_fn(|| lexer.next_tuple().map(parsed)).collect::<Vec<_>>();
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0], Ok(rt::tuple!(E::I(1))));
+    let err = results[1].as_ref().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (5, TokenKind::Element));
+    let err = results[2].as_ref().unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (8, TokenKind::Tuple));
+    assert_eq!(results[3], Ok(rt::tuple!(E::I(2))));
+    let err = results[4].as_ref().unwrap_err();
+    assert_eq!((err.offset(), err.message()), (17, "invalid UTF-8"));
+
+    // Offsets count from the start of the stream, and the end of the stream may cut a tuple off.
+    lexer.feed(b" (3, \"open");
+    assert!(lexer.next_tuple().is_none());
+    let err = parsed(lexer.finish()).unwrap_err();
+    assert_eq!((err.offset(), err.expected()), (30, TokenKind::Char('"')));
+    lexer.feed(b"(4)");
+    assert_eq!(
+        lexer.next_tuple().map(parsed),
+        Some(Ok(rt::tuple!(E::I(4))))
+    );
+}
+
+#[test]
+fn test_tuple_reader() {
+    /// Hands out one byte per read.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    /// Hands out its input and then fails.
+    struct Failing<'a>(&'a [u8]);
+
+    impl Read for Failing<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("connection reset"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    let input = (0..10_000)
+        .map(|i| format!("({i}, \"t{i}\", {i}.5)\n"))
+        .collect::<String>();
+    let tuples = TupleReader::new(input.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tuples.len(), 10_000);
+    assert_eq!(
+        tuples[9_999],
+        rt::tuple!(E::I(9_999), E::str("t9999"), E::D(9_999.5))
+    );
+
+    let results = TupleReader::new(Trickle(b"(1), (oops), (\"\xc3\xa9\"), (2"))
+        .map(|r| parsed(r).map_err(|e| e.expected()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![
+            Ok(rt::tuple!(E::I(1))),
+            Err(TokenKind::Element),
+            Ok(rt::tuple!(E::str("é"))),
+            Err(TokenKind::Char(')')),
+        ]
+    );
+
+    // Reading stops at the first I/O error.
+    let mut results = TupleReader::new(Failing(b"(1) (2"));
+    assert_eq!(results.next().map(parsed), Some(Ok(rt::tuple!(E::I(1)))));
+    assert!(matches!(results.next(), Some(Err(rt::Error::Io(_)))));
+    assert!(results.next().is_none());
+}
+
+#[test]
+fn test_canonical_floats() {
+    assert_eq!(E::D(1.0).to_string(), "1.0");
+    assert_eq!(E::D(-0.0).to_string(), "-0.0");
+    assert_eq!(E::D(1e300).to_string().parse::<f64>(), Ok(1e300));
+    assert_eq!(E::D(f64::INFINITY).to_string(), "inf");
+    assert_eq!(E::D(f64::NEG_INFINITY).to_string(), "-inf");
+    assert_eq!(E::D(f64::NAN).to_string(), "nan");
+    assert_eq!(E::None.to_string(), "nil");
+
+    let tup = parse_tuple("(1, 1.0, -0.0, inf, -inf, nan, nil)").unwrap();
+    assert_eq!(tup[0], E::I(1));
+    assert_eq!(tup[1], E::D(1.0));
+    assert!(matches!(tup[2], E::D(d) if d.to_bits() == (-0.0f64).to_bits()));
+    assert_eq!(tup[3], E::D(f64::INFINITY));
+    assert_eq!(tup[4], E::D(f64::NEG_INFINITY));
+    assert!(matches!(tup[5], E::D(d) if d.is_nan()));
+    assert_eq!(tup[6], E::None);
+    assert_eq!(tup.to_string(), "(1,1.0,-0.0,inf,-inf,nan,nil)");
+}
+
+/// Values of every type, nested up to three tuples deep. NaN is left out, since it never equals
+/// itself.
+fn value() -> impl Strategy<Value = E> {
+    let leaf = prop_oneof![
+        any::<i32>().prop_map(E::I),
+        any::<i64>().prop_map(E::L),
+        any::<u64>().prop_map(E::U),
+        any::<f64>()
+            .prop_filter("NaN", |d| !d.is_nan())
+            .prop_map(E::D),
+        any::<bool>().prop_map(E::B),
+        any::<String>().prop_map(E::S),
+        any::<Vec<u8>>().prop_map(E::R),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop::collection::vec(inner, 0..4).prop_map(|v| E::T(rt::Tuple::from_vec(v)))
+    })
+}
+
+fn bound() -> impl Strategy<Value = Bound<E>> {
+    prop_oneof![
+        Just(Bound::Unbounded),
+        value().prop_map(Bound::Included),
+        value().prop_map(Bound::Excluded),
+    ]
+}
+
+fn constraint() -> impl Strategy<Value = Constraint> {
+    prop_oneof![
+        (bound(), bound()).prop_map(|(lo, hi)| Constraint::Range(lo, hi)),
+        any::<String>().prop_map(Constraint::Prefix),
+        prop::sample::select(vec!["^t.+k$", r"\d+\.\d*", "a|\"b\"", r"\\", "[ä-ü]\n"])
+            .prop_map(|r| Constraint::Regex(Regex::new(r).unwrap())),
+        prop::collection::vec(value(), 0..4).prop_map(Constraint::OneOf),
+    ]
+}
+
+/// Elements of every variant, with templates nested in tuples.
+fn element() -> impl Strategy<Value = E> {
+    let types = vec![
+        Type::Int,
+        Type::Long,
+        Type::Unsigned,
+        Type::Float,
+        Type::Bool,
+        Type::Str,
+        Type::Bytes,
+        Type::Tuple,
+    ];
+    let leaf = prop_oneof![
+        value(),
+        Just(E::Any),
+        Just(E::None),
+        prop::sample::select(types).prop_map(E::AnyOf),
+        constraint().prop_map(|c| E::Where(Box::new(c))),
+    ];
+    leaf.prop_recursive(2, 16, 4, |inner| {
+        prop::collection::vec(inner, 0..4).prop_map(|v| E::T(rt::Tuple::from_vec(v)))
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_display_round_trip(elements in prop::collection::vec(element(), 0..6)) {
+        let tup = rt::Tuple::from_vec(elements);
+        let text = tup.to_string();
+        let parsed = parsed(parse_tuple(&text));
+        prop_assert_eq!(parsed.as_ref(), Ok(&tup), "{}", text);
+        prop_assert_eq!(parsed.unwrap().to_string(), text);
+    }
+}
+
 fn check_output(input: &str, expected: &[rt::tuple::Tuple]) {
     let output_tuples: Vec<rt::Tuple> = Lexer::new(input).collect();
 
@@ -0,0 +1,436 @@
+#[macro_use]
+extern crate rustupolis;
+
+use rustupolis::error::Error;
+use rustupolis::persistent::{Fsync, PersistentStore};
+use rustupolis::store::{IndexedStore, Lease, Policy, SimpleStore, Store};
+use rustupolis::tuple::{Tuple, E};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use std::{env, thread};
+
+/// Set for the child process of `test_kill_during_write` to the log it writes to.
+const WRITER: &str = "RUSTUPOLIS_WAL_WRITER";
+
+fn open(path: &Path) -> PersistentStore<IndexedStore> {
+    PersistentStore::open(path, IndexedStore::new(), Fsync::Always).unwrap()
+}
+
+fn job(i: i32) -> Tuple {
+    tuple![E::str("job"), E::I(i)]
+}
+
+#[test]
+fn test_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    {
+        let mut ps = open(&path);
+        for i in 0..5 {
+            ps.out(job(i)).unwrap();
+        }
+        ps.out(job(3)).unwrap();
+        ps.out(tuple![E::str("done"), E::D(0.5)]).unwrap();
+        assert_eq!(
+            ps.inp(&tuple![E::str("job"), E::Any]).unwrap(),
+            Some(job(0))
+        );
+        assert_eq!(ps.in_all(&job(3)).unwrap(), vec![job(3), job(3)]);
+        assert!(matches!(
+            ps.out(tuple![E::str("job"), E::Any]),
+            Err(Error::UndefinedTuple)
+        ));
+    }
+    let mut ps = open(&path);
+    assert_eq!(ps.store().len(), 4);
+    assert_eq!(
+        ps.in_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![job(1), job(2), job(4)]
+    );
+    assert_eq!(
+        ps.rdp(&tuple![E::str("done"), E::Any]).unwrap(),
+        Some(tuple![E::str("done"), E::D(0.5)])
+    );
+    drop(ps);
+    assert_eq!(open(&path).store().len(), 1);
+}
+
+#[test]
+fn test_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let lifo =
+        || PersistentStore::open(&path, SimpleStore::with_policy(Policy::Lifo), Fsync::Never);
+    {
+        let mut ps = lifo().unwrap();
+        assert_eq!(ps.policy(), Policy::Lifo);
+        for i in 0..3 {
+            ps.out(job(i)).unwrap();
+        }
+        assert_eq!(ps.inp(&tuple![E::Any, E::Any]).unwrap(), Some(job(2)));
+    }
+    let mut ps = lifo().unwrap();
+    assert_eq!(ps.inp(&tuple![E::Any, E::Any]).unwrap(), Some(job(1)));
+}
+
+#[test]
+fn test_leases() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let (renewed, cancelled) = {
+        let mut ps = open(&path);
+        let short = ps.out_with_ttl(job(1), Duration::from_millis(50)).unwrap();
+        let renewed = ps.out_with_ttl(job(2), Duration::from_millis(50)).unwrap();
+        let cancelled = ps.out_with_ttl(job(3), Duration::from_secs(60)).unwrap();
+        ps.out(job(3)).unwrap();
+        assert!(ps.renew(&renewed, Duration::from_secs(60)).unwrap());
+        assert!(ps.cancel(&cancelled).unwrap());
+        assert!(!ps.cancel(&cancelled).unwrap());
+        thread::sleep(Duration::from_millis(100));
+        assert!(!ps.renew(&short, Duration::from_secs(60)).unwrap());
+        (renewed, cancelled)
+    };
+    let mut ps = open(&path);
+    // the lease ran out while the store was closed, the renewed one is still running
+    assert_eq!(
+        ps.rd_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![job(2), job(3)]
+    );
+    assert_eq!(ps.purge_expired().unwrap(), 1);
+    assert!(!ps.cancel(&cancelled).unwrap());
+    assert!(ps.renew(&renewed, Duration::from_millis(50)).unwrap());
+    drop(ps);
+
+    thread::sleep(Duration::from_millis(100));
+    let mut ps = open(&path);
+    assert_eq!(
+        ps.rd_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![job(3)]
+    );
+    assert!(!ps.cancel(&renewed).unwrap());
+}
+
+#[test]
+fn test_long_ttl() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let lease = {
+        let mut ps = open(&path);
+        let lease = ps.out_with_ttl(job(1), Duration::MAX).unwrap();
+        assert!(ps.renew(&lease, Duration::from_secs(u64::MAX / 2)).unwrap());
+        lease
+    };
+    let mut ps = open(&path);
+    assert_eq!(ps.purge_expired().unwrap(), 0);
+    assert_eq!(ps.rdp(&job(1)).unwrap(), Some(job(1)));
+    assert!(ps.renew(&lease, Duration::MAX).unwrap());
+    assert!(ps.cancel(&lease).unwrap());
+}
+
+#[test]
+fn test_leased_and_unleased_copies() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let lease = {
+        let mut ps = open(&path);
+        // the oldest copy is taken, with or without a lease
+        ps.out_with_ttl(job(1), Duration::from_millis(200)).unwrap();
+        ps.out(job(1)).unwrap();
+        assert_eq!(ps.inp(&job(1)).unwrap(), Some(job(1)));
+        ps.out(job(2)).unwrap();
+        let lease = ps.out_with_ttl(job(2), Duration::from_secs(60)).unwrap();
+        assert_eq!(ps.inp(&job(2)).unwrap(), Some(job(2)));
+        lease
+    };
+    {
+        let mut ps = open(&path);
+        assert!(ps.cancel(&lease).unwrap());
+    }
+    thread::sleep(Duration::from_millis(300));
+    let mut ps = open(&path);
+    assert_eq!(ps.rdp(&job(1)).unwrap(), Some(job(1)));
+    assert_eq!(ps.rdp(&job(2)).unwrap(), None);
+    assert_eq!(ps.purge_expired().unwrap(), 0);
+    assert_eq!(ps.store().len(), 1);
+}
+
+#[test]
+fn test_compact() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let lease = {
+        let mut ps = open(&path);
+        for i in 0..100 {
+            ps.out(job(i)).unwrap();
+        }
+        ps.out(job(1)).unwrap();
+        let task = tuple![E::str("task"), E::I(2)];
+        let lease = ps.out_with_ttl(task, Duration::from_secs(60)).unwrap();
+        ps.out(tuple![E::str("done")]).unwrap();
+        assert_eq!(
+            ps.in_all(&tuple![E::str("job"), E::Any]).unwrap().len(),
+            101
+        );
+        ps.out(job(1)).unwrap();
+        let before = fs::metadata(&path).unwrap().len();
+        ps.compact().unwrap();
+        assert!(fs::metadata(&path).unwrap().len() < before / 10);
+        // changes made after the compaction are appended to the snapshot
+        ps.out(job(3)).unwrap();
+        assert_eq!(ps.inp(&job(1)).unwrap(), Some(job(1)));
+        lease
+    };
+    let mut ps = open(&path);
+    assert_eq!(
+        ps.rd_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![job(3)]
+    );
+    assert_eq!(
+        ps.rdp(&tuple![E::str("done")]).unwrap(),
+        Some(tuple![E::str("done")])
+    );
+    // leases handed out before the compaction stay valid
+    assert!(ps.renew(&lease, Duration::from_secs(60)).unwrap());
+    ps.compact().unwrap();
+    assert!(ps.cancel(&lease).unwrap());
+    drop(ps);
+    assert_eq!(open(&path).store().len(), 2);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_rejected_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    {
+        let mut ps =
+            PersistentStore::open(&path, Bounded(IndexedStore::new()), Fsync::Always).unwrap();
+        ps.out(job(1)).unwrap();
+        assert!(matches!(ps.out(job(2)), Err(Error::CapacityExceeded)));
+        assert!(matches!(
+            ps.out_with_ttl(job(3), Duration::from_secs(60)),
+            Err(Error::CapacityExceeded)
+        ));
+    }
+    // tuples the store did not accept are not replayed
+    assert_eq!(
+        open(&path).rd_all(&tuple![E::Any, E::Any]).unwrap(),
+        vec![job(1)]
+    );
+}
+
+/// A store that holds a single tuple.
+struct Bounded(IndexedStore);
+
+impl Store for Bounded {
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.0.inp(tup)
+    }
+
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.0.rdp(tup)
+    }
+
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.0.in_all(tup)
+    }
+
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.0.rd_all(tup)
+    }
+
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
+        if !self.0.is_empty() {
+            return Err(Error::CapacityExceeded);
+        }
+        self.0.out(tup)
+    }
+
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error> {
+        if !self.0.is_empty() {
+            return Err(Error::CapacityExceeded);
+        }
+        self.0.out_with_ttl(tup, ttl)
+    }
+
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        self.0.renew(lease, ttl)
+    }
+
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        self.0.cancel(lease)
+    }
+
+    fn purge_expired(&mut self) -> Result<usize, Error> {
+        self.0.purge_expired()
+    }
+
+    fn policy(&self) -> Policy {
+        self.0.policy()
+    }
+}
+
+#[test]
+fn test_torn_tail() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    {
+        let mut ps = open(&path);
+        for i in 0..3 {
+            ps.out(job(i)).unwrap();
+        }
+    }
+    let complete = fs::read(&path).unwrap();
+    let cut = dir.path().join("cut.wal");
+    {
+        let mut ps = open(&cut);
+        ps.out(job(0)).unwrap();
+        ps.out(job(1)).unwrap();
+    }
+    let two = fs::read(&cut).unwrap().len();
+
+    // every write of the last record that was cut short is discarded
+    for len in two..complete.len() {
+        fs::write(&cut, &complete[..len]).unwrap();
+        let mut ps = open(&cut);
+        assert_eq!(ps.store().len(), 2, "cut at {len}");
+        ps.out(job(5)).unwrap();
+        drop(ps);
+        assert_eq!(
+            open(&cut).rd_all(&tuple![E::Any, E::Any]).unwrap(),
+            vec![job(0), job(1), job(5)]
+        );
+    }
+
+    // so is a last record that was written with garbage
+    let mut garbled = complete.clone();
+    let last = garbled.len() - 1;
+    garbled[last] ^= 0xff;
+    fs::write(&cut, &garbled).unwrap();
+    assert_eq!(open(&cut).store().len(), 2);
+    assert_eq!(fs::read(&cut).unwrap().len(), two);
+
+    // and the zeros of a file that was extended without being written
+    let mut zeroed = complete.clone();
+    zeroed.extend([0; 100]);
+    fs::write(&cut, &zeroed).unwrap();
+    assert_eq!(open(&cut).store().len(), 3);
+    assert_eq!(fs::read(&cut).unwrap(), complete);
+
+    // but a damaged record before the last one is not a torn write
+    let mut damaged = complete.clone();
+    damaged[10] ^= 0xff;
+    fs::write(&cut, &damaged).unwrap();
+    assert!(matches!(
+        PersistentStore::open(&cut, IndexedStore::new(), Fsync::Always),
+        Err(Error::Io(_))
+    ));
+    assert_eq!(fs::read(&cut).unwrap(), damaged);
+
+    // neither is a damaged length of one, whatever it claims
+    let second = u32::from_le_bytes(complete[..4].try_into().unwrap()) as usize + 8;
+    for (byte, flip) in [(0, 0x01), (0, 0xff), (3, 0x80)] {
+        let mut damaged = complete.clone();
+        damaged[second + byte] ^= flip;
+        fs::write(&cut, &damaged).unwrap();
+        assert!(matches!(
+            PersistentStore::open(&cut, IndexedStore::new(), Fsync::Always),
+            Err(Error::Io(_))
+        ));
+        assert_eq!(fs::read(&cut).unwrap(), damaged);
+    }
+}
+
+#[test]
+fn test_fsync_policies() {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, fsync) in [
+        ("always", Fsync::Always),
+        ("batched", Fsync::Batched(10)),
+        ("never", Fsync::Never),
+    ] {
+        let path = dir.path().join(name);
+        {
+            let mut ps = PersistentStore::open(&path, IndexedStore::new(), fsync).unwrap();
+            for i in 0..25 {
+                ps.out(job(i)).unwrap();
+            }
+            ps.sync().unwrap();
+        }
+        let ps = PersistentStore::open(&path, IndexedStore::new(), fsync).unwrap();
+        assert_eq!(ps.store().len(), 25, "{name}");
+    }
+}
+
+#[test]
+fn test_unreadable_log() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(matches!(
+        PersistentStore::open(dir.path(), IndexedStore::new(), Fsync::Always),
+        Err(Error::Io(_))
+    ));
+}
+
+/// The child process of `test_kill_during_write`: puts in numbered tuples from where the log
+/// left off and reports every one that was acknowledged, until it is killed.
+#[test]
+fn kill_writer() {
+    let Ok(path) = env::var(WRITER) else {
+        return;
+    };
+    let mut ps = PersistentStore::open(path, IndexedStore::new(), Fsync::Never).unwrap();
+    let payload = "x".repeat(4096);
+    for i in ps.store().len() as i32.. {
+        ps.out(tuple![E::I(i), E::str(&payload)]).unwrap();
+        println!("acked {i}");
+    }
+}
+
+#[test]
+fn test_kill_during_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("space.wal");
+    let mut acked = 0;
+    for _ in 0..5 {
+        let mut writer = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "kill_writer", "--nocapture", "--test-threads=1"])
+            .env(WRITER, &path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut lines = BufReader::new(writer.stdout.take().unwrap()).lines();
+        let mut acks = 0;
+        while acks < 200 {
+            let line = lines.next().unwrap().unwrap();
+            if let Some(i) = line.split("acked ").nth(1) {
+                acked = acked.max(i.parse::<i32>().unwrap() + 1);
+                acks += 1;
+            }
+        }
+        writer.kill().unwrap();
+        writer.wait().unwrap();
+        // acknowledgements that were still in the pipe when the writer was killed
+        for line in lines {
+            if let Some(i) = line.unwrap().split("acked ").nth(1) {
+                acked = acked.max(i.parse::<i32>().unwrap() + 1);
+            }
+        }
+
+        let mut ps = open(&path);
+        let mut found = ps
+            .rd_all(&tuple![E::Any, E::Any])
+            .unwrap()
+            .into_iter()
+            .map(|tup| match tup.first() {
+                E::I(i) => *i,
+                e => panic!("unexpected {e}"),
+            })
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        assert!(found.len() >= acked as usize);
+        assert_eq!(found, (0..found.len() as i32).collect::<Vec<_>>());
+    }
+}
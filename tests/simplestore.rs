@@ -1,8 +1,13 @@
 #[macro_use]
 extern crate rustupolis;
 
-use rustupolis::store::{SimpleStore, Store};
-use rustupolis::tuple::E;
+use rustupolis::constraint::Constraint;
+use rustupolis::error::Error;
+use rustupolis::store::{Policy, SimpleStore, Store};
+use rustupolis::tuple::{Tuple, Type, E};
+use std::collections::Bound;
+use std::thread;
+use std::time::Duration;
 
 #[test]
 fn test_len() {
@@ -14,11 +19,50 @@ fn test_len() {
     assert_eq!(ss.len(), 42);
 }
 
+#[test]
+fn test_duplicates() {
+    let mut ss = SimpleStore::new();
+    for _ in 0..10 {
+        ss.out(tuple![E::str("token")]).unwrap();
+    }
+    ss.out(tuple![E::str("token"), E::I(1)]).unwrap();
+    assert_eq!(ss.len(), 11);
+    assert_eq!(ss.rd_all(&tuple![E::str("token")]).unwrap().len(), 10);
+    for taken in 1..=10 {
+        assert_eq!(
+            ss.inp(&tuple![E::str("token")]).unwrap(),
+            Some(tuple![E::str("token")])
+        );
+        assert_eq!(ss.len(), 11 - taken);
+    }
+    assert_eq!(ss.inp(&tuple![E::str("token")]).unwrap(), None);
+
+    // copies are taken out at once by in_all, each with a lease of its own
+    let short = ss
+        .out_with_ttl(tuple![E::str("token")], Duration::from_millis(50))
+        .unwrap();
+    let long = ss
+        .out_with_ttl(tuple![E::str("token")], Duration::from_secs(60))
+        .unwrap();
+    ss.out(tuple![E::str("token")]).unwrap();
+    assert_ne!(short, long);
+    thread::sleep(Duration::from_millis(100));
+    assert!(!ss.renew(&short, Duration::from_secs(60)).unwrap());
+    assert!(ss.renew(&long, Duration::from_secs(60)).unwrap());
+    assert_eq!(ss.in_all(&tuple![E::str("token")]).unwrap().len(), 2);
+    assert_eq!(ss.purge_expired().unwrap(), 1);
+    assert!(!ss.cancel(&long).unwrap());
+    assert_eq!(ss.len(), 1);
+}
+
 #[test]
 fn test_out() {
     let mut ss = SimpleStore::new();
     let tup = tuple![E::S("test content".to_string())];
-    assert!(ss.out(tup).is_ok())
+    assert!(ss.out(tup).is_ok());
+    let err = ss.out(tuple![E::I(1), E::Any]).unwrap_err();
+    assert!(matches!(err, Error::UndefinedTuple));
+    assert_eq!(err.to_string(), "attempted to insert an undefined tuple");
 }
 
 #[test]
@@ -29,7 +73,7 @@ fn test_rdp() {
     let tup3 = tuple![E::D(3.143), E::Any];
 
     ss.out(tup1).unwrap();
-    let tup4 = ss.rdp(&tup2).unwrap();
+    let tup4 = ss.rdp(&tup2).unwrap().unwrap();
 
     assert!(tup3.matches(&tup4));
 }
@@ -43,7 +87,7 @@ fn test_inp() {
 
     ss.out(tup1).unwrap();
     assert_eq!(ss.len(), 1);
-    let tup4 = ss.inp(&tup2);
+    let tup4 = ss.inp(&tup2).unwrap();
     assert_eq!(ss.len(), 0);
 
     match tup4 {
@@ -59,7 +103,7 @@ fn test_empty_tuple() {
     let mut ss = SimpleStore::new();
     let tup1 = tuple![];
     ss.out(tup1.clone()).unwrap();
-    assert_eq!(ss.inp(&tup1), Some(tup1.clone()));
+    assert_eq!(ss.inp(&tup1).unwrap(), Some(tup1.clone()));
 }
 
 #[test]
@@ -67,9 +111,9 @@ fn test_contains_empty_tuple() {
     let mut ss = SimpleStore::new();
     let tup1 = tuple![E::T(tuple![])];
     ss.out(tup1.clone()).unwrap();
-    assert_eq!(ss.inp(&tuple![E::Any]), Some(tup1.clone()));
+    assert_eq!(ss.inp(&tuple![E::Any]).unwrap(), Some(tup1.clone()));
     ss.out(tup1.clone()).unwrap();
-    assert_eq!(ss.inp(&tuple![E::T(tuple![])]), Some(tup1));
+    assert_eq!(ss.inp(&tuple![E::T(tuple![])]).unwrap(), Some(tup1));
 }
 
 #[test]
@@ -77,7 +121,7 @@ fn test_match_defined() {
     let mut ss = SimpleStore::new();
     let tup1 = tuple![E::I(123)];
     ss.out(tup1.clone()).unwrap();
-    assert_eq!(ss.inp(&tup1), Some(tup1.clone()));
+    assert_eq!(ss.inp(&tup1).unwrap(), Some(tup1.clone()));
 }
 
 #[test]
@@ -85,5 +129,267 @@ fn test_match_nested() {
     let mut ss = SimpleStore::new();
     let tup1 = tuple![E::I(123), E::T(tuple![E::str("hello")])];
     ss.out(tup1.clone()).unwrap();
-    assert_eq!(ss.inp(&tup1), Some(tup1.clone()));
+    assert_eq!(ss.inp(&tup1).unwrap(), Some(tup1.clone()));
+}
+
+#[test]
+fn test_match_arity() {
+    let mut ss = SimpleStore::new();
+    ss.out(tuple![E::I(1), E::I(2), E::I(3)]).unwrap();
+    assert_eq!(ss.rdp(&tuple![E::I(1), E::Any]).unwrap(), None);
+    assert_eq!(ss.rdp(&tuple![E::I(1)]).unwrap(), None);
+    assert_eq!(
+        ss.rdp(&tuple![E::I(1), E::Any, E::Any]).unwrap(),
+        Some(tuple![E::I(1), E::I(2), E::I(3)])
+    );
+}
+
+#[test]
+fn test_bulk() {
+    let mut ss = SimpleStore::new();
+    for i in 0..10 {
+        ss.out(tuple![E::str("result"), E::I(i)]).unwrap();
+    }
+    ss.out(tuple![E::str("task"), E::I(0)]).unwrap();
+
+    let results = (0..10)
+        .map(|i| tuple![E::str("result"), E::I(i)])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ss.rd_all(&tuple![E::str("result"), E::Any]).unwrap(),
+        results
+    );
+    assert_eq!(ss.len(), 11);
+    assert_eq!(
+        ss.in_all(&tuple![E::str("result"), E::Any]).unwrap(),
+        results
+    );
+    assert_eq!(ss.len(), 1);
+    assert!(ss
+        .in_all(&tuple![E::str("result"), E::Any])
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        ss.in_all(&tuple![E::str("task"), E::I(0)]).unwrap(),
+        vec![tuple![E::str("task"), E::I(0)]]
+    );
+    assert!(ss.is_empty());
+}
+
+#[test]
+fn test_ttl() {
+    let mut ss = SimpleStore::new();
+    let short = ss
+        .out_with_ttl(tuple![E::I(1)], Duration::from_millis(50))
+        .unwrap();
+    let long = ss
+        .out_with_ttl(tuple![E::I(2)], Duration::from_secs(60))
+        .unwrap();
+    ss.out(tuple![E::I(3)]).unwrap();
+    assert_eq!(short.tuple(), &tuple![E::I(1)]);
+    assert!(matches!(
+        ss.out_with_ttl(tuple![E::Any], Duration::from_secs(1)),
+        Err(Error::UndefinedTuple)
+    ));
+    assert_eq!(ss.rd_all(&tuple![E::Any]).unwrap().len(), 3);
+
+    thread::sleep(Duration::from_millis(100));
+    // expired tuples are invisible but only reclaimed by a purge
+    assert_eq!(ss.rdp(&tuple![E::I(1)]).unwrap(), None);
+    assert_eq!(ss.inp(&tuple![E::I(1)]).unwrap(), None);
+    assert_eq!(
+        ss.rd_all(&tuple![E::Any]).unwrap(),
+        vec![tuple![E::I(2)], tuple![E::I(3)]]
+    );
+    assert!(!ss.renew(&short, Duration::from_secs(60)).unwrap());
+    assert_eq!(ss.len(), 3);
+    assert_eq!(ss.purge_expired().unwrap(), 1);
+    assert_eq!(ss.len(), 2);
+
+    assert!(ss.renew(&long, Duration::from_secs(60)).unwrap());
+    assert!(ss.cancel(&long).unwrap());
+    assert!(!ss.cancel(&long).unwrap());
+    assert_eq!(ss.rdp(&tuple![E::I(2)]).unwrap(), None);
+    assert_eq!(ss.len(), 1);
+
+    // putting a leased tuple in again without a ttl adds a permanent copy
+    let lease = ss
+        .out_with_ttl(tuple![E::I(4)], Duration::from_millis(50))
+        .unwrap();
+    ss.out(tuple![E::I(4)]).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert!(!ss.renew(&lease, Duration::from_secs(60)).unwrap());
+    assert_eq!(ss.rdp(&tuple![E::I(4)]).unwrap(), Some(tuple![E::I(4)]));
+}
+
+#[test]
+fn test_long_ttl() {
+    let mut ss = SimpleStore::new();
+    // a lease too long to be represented never expires
+    let lease = ss.out_with_ttl(tuple![E::I(1)], Duration::MAX).unwrap();
+    assert!(ss.renew(&lease, Duration::from_secs(u64::MAX / 2)).unwrap());
+    assert_eq!(ss.purge_expired().unwrap(), 0);
+    assert_eq!(ss.rdp(&tuple![E::Any]).unwrap(), Some(tuple![E::I(1)]));
+    assert!(ss.cancel(&lease).unwrap());
+    assert!(ss.is_empty());
+}
+
+#[test]
+fn test_nan() {
+    let mut ss = SimpleStore::new();
+    let nan = tuple![E::str("x"), E::D(f64::NAN)];
+    ss.out(nan.clone()).unwrap();
+    ss.out(nan.clone()).unwrap();
+    ss.out(tuple![E::str("x"), E::D(0.0)]).unwrap();
+    let lease = ss
+        .out_with_ttl(nan.clone(), Duration::from_secs(60))
+        .unwrap();
+
+    // a NaN is found by itself, and floats are told apart by their bits
+    assert!(ss.rdp(&nan).unwrap().is_some_and(|t| nan.matches(&t)));
+    assert_eq!(ss.rd_all(&nan).unwrap().len(), 3);
+    assert_eq!(ss.rdp(&tuple![E::str("x"), E::D(-0.0)]).unwrap(), None);
+    assert!(ss.renew(&lease, Duration::from_secs(60)).unwrap());
+    assert!(ss.cancel(&lease).unwrap());
+    for _ in 0..2 {
+        assert!(ss.inp(&nan).unwrap().is_some_and(|t| nan.matches(&t)));
+    }
+    assert_eq!(ss.inp(&nan).unwrap(), None);
+    assert_eq!(
+        ss.in_all(&tuple![E::Any, E::Any]).unwrap(),
+        vec![tuple![E::str("x"), E::D(0.0)]]
+    );
+    assert!(ss.is_empty());
+}
+
+#[test]
+fn test_typed_wildcards() {
+    let mut ss = SimpleStore::new();
+    for e in [
+        E::I(1),
+        E::L(2),
+        E::D(3.0),
+        E::str("4"),
+        E::T(tuple![E::I(5)]),
+    ] {
+        ss.out(tuple![E::str("key"), e]).unwrap();
+    }
+    assert_eq!(
+        ss.rd_all(&tuple![E::str("key"), E::AnyOf(Type::Int)])
+            .unwrap(),
+        vec![tuple![E::str("key"), E::I(1)]]
+    );
+    assert_eq!(
+        ss.rdp(&tuple![E::str("key"), E::AnyOf(Type::Tuple)])
+            .unwrap(),
+        Some(tuple![E::str("key"), E::T(tuple![E::I(5)])])
+    );
+    assert_eq!(
+        ss.inp(&tuple![E::AnyOf(Type::Str), E::AnyOf(Type::Float)])
+            .unwrap(),
+        Some(tuple![E::str("key"), E::D(3.0)])
+    );
+    assert_eq!(
+        ss.rdp(&tuple![E::str("key"), E::AnyOf(Type::Float)])
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        ss.rdp(&tuple![E::str("key"), E::AnyOf(Type::Bool)])
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_constraints() {
+    let mut ss = SimpleStore::new();
+    for priority in 0..10 {
+        ss.out(tuple![E::str("task"), E::I(priority)]).unwrap();
+    }
+    let urgent = tuple![
+        E::str("task"),
+        E::Where(Box::new(Constraint::Range(
+            Bound::Included(E::I(5)),
+            Bound::Unbounded
+        )))
+    ];
+    assert_eq!(
+        ss.inp(&urgent).unwrap(),
+        Some(tuple![E::str("task"), E::I(5)])
+    );
+    assert_eq!(ss.rd_all(&urgent).unwrap().len(), 4);
+
+    let some = tuple![
+        E::Where(Box::new(Constraint::Prefix("ta".to_string()))),
+        E::Where(Box::new(Constraint::OneOf(vec![E::I(1), E::I(3), E::I(5)])))
+    ];
+    assert_eq!(
+        ss.in_all(&some).unwrap(),
+        vec![
+            tuple![E::str("task"), E::I(1)],
+            tuple![E::str("task"), E::I(3)]
+        ]
+    );
+
+    // Contradicting bounds match nothing.
+    let empty = tuple![
+        E::str("task"),
+        E::Where(Box::new(Constraint::Range(
+            Bound::Excluded(E::I(5)),
+            Bound::Included(E::I(5))
+        )))
+    ];
+    assert_eq!(ss.rdp(&empty).unwrap(), None);
+    let inverted = tuple![E::Where(Box::new(Constraint::Range(
+        Bound::Included(E::I(5)),
+        Bound::Excluded(E::I(1))
+    )))];
+    assert_eq!(ss.rdp(&inverted).unwrap(), None);
+}
+
+fn jobs(policy: Policy) -> SimpleStore {
+    let mut ss = SimpleStore::with_policy(policy);
+    for job in ["c", "a", "b", "a"] {
+        ss.out(tuple![E::str("job"), E::str(job)]).unwrap();
+    }
+    ss
+}
+
+fn job(name: &str) -> Tuple {
+    tuple![E::str("job"), E::str(name)]
+}
+
+#[test]
+fn test_policy() {
+    let template = tuple![E::str("job"), E::Any];
+    assert_eq!(SimpleStore::new().policy(), Policy::Ordered);
+
+    let mut ss = jobs(Policy::Ordered);
+    assert_eq!(ss.inp(&template).unwrap(), Some(job("a")));
+    assert_eq!(
+        ss.rd_all(&template).unwrap(),
+        vec![job("a"), job("b"), job("c")]
+    );
+
+    let mut ss = jobs(Policy::Fifo);
+    assert_eq!(ss.rdp(&template).unwrap(), Some(job("c")));
+    assert_eq!(ss.inp(&template).unwrap(), Some(job("c")));
+    assert_eq!(
+        ss.in_all(&template).unwrap(),
+        vec![job("a"), job("b"), job("a")]
+    );
+
+    let mut ss = jobs(Policy::Lifo);
+    assert_eq!(ss.inp(&template).unwrap(), Some(job("a")));
+    assert_eq!(ss.inp(&template).unwrap(), Some(job("b")));
+    assert_eq!(ss.rd_all(&template).unwrap(), vec![job("a"), job("c")]);
+
+    let mut ss = jobs(Policy::Random);
+    let mut taken = (0..4)
+        .map(|_| ss.inp(&template).unwrap().unwrap())
+        .collect::<Vec<_>>();
+    taken.sort();
+    assert_eq!(taken, vec![job("a"), job("a"), job("b"), job("c")]);
+    assert!(ss.is_empty());
 }
@@ -1,17 +1,18 @@
 #[macro_use]
 extern crate rustupolis;
-use futures::executor;
-use rustupolis::space::Space;
-use rustupolis::store::SimpleStore;
-use rustupolis::tuple::E;
+use futures::{executor, FutureExt, StreamExt};
+use rustupolis::error::Error;
+use rustupolis::eval::Field;
+use rustupolis::space::{SharedSpace, Space};
+use rustupolis::store::{Lease, Policy, SimpleStore, Store};
+use rustupolis::tuple::{Tuple, Type, E};
+use std::time::Duration;
 
 // extern crate futures;
 // use std::future::task::Unpark;
 // use std::future::Async;
 // use std::future::Future;
 
-/// This test is faulty for now and results in a infinite loop when run with Travis CI.
-// #[ignore]
 #[test]
 fn test_in() {
     pretty_env_logger::init();
@@ -48,7 +49,703 @@ fn test_in() {
     let retrieval_result = executor::block_on(retrieval_future);
 
     match retrieval_result {
-        Some(ref t) => assert_eq!(t, &tuple![E::str("foo"), E::I(42)]),
+        Ok(ref t) => assert_eq!(t, &tuple![E::str("foo"), E::I(42)]),
         a => panic!("{:?}", a),
     };
 }
+
+#[test]
+fn test_pending_in() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    // nothing to take yet, the match has to wait
+    let mut pending = sp.tuple_in(tuple![E::str("foo"), E::Any]);
+    assert!((&mut pending).now_or_never().is_none());
+
+    executor::block_on(sp.tuple_out(tuple![E::str("foo"), E::I(42)])).unwrap();
+    assert_eq!(
+        executor::block_on(pending).unwrap(),
+        tuple![E::str("foo"), E::I(42)]
+    );
+
+    // the tuple has been handed over to the waiter instead of the store
+    assert!(sp
+        .tuple_in(tuple![E::str("foo"), E::Any])
+        .now_or_never()
+        .is_none());
+
+    // a match still waiting when the space is dropped fails instead of waiting forever
+    let pending = sp.tuple_in(tuple![E::str("foo"), E::Any]);
+    drop(sp);
+    assert!(matches!(
+        executor::block_on(pending),
+        Err(Error::WaiterDisconnected)
+    ));
+}
+
+#[test]
+fn test_pending_rd_dropped() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    // a waiter that gives up must not swallow the next tuple
+    drop(sp.tuple_rd(tuple![E::Any]));
+    executor::block_on(sp.tuple_out(tuple![E::I(7)])).unwrap();
+    assert_eq!(
+        executor::block_on(sp.tuple_rd(tuple![E::Any])).unwrap(),
+        tuple![E::I(7)]
+    );
+}
+
+#[test]
+fn test_shared_space_blocking_in() {
+    let sp = SharedSpace::new(SimpleStore::new());
+
+    // the waiting threads must not keep the space locked
+    let handles = (0..4)
+        .map(|_| {
+            let sp = sp.clone();
+            std::thread::spawn(move || {
+                executor::block_on(sp.tuple_in(tuple![E::str("job"), E::Any]))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for i in 0..4 {
+        executor::block_on(sp.tuple_out(tuple![E::str("job"), E::I(i)])).unwrap();
+    }
+
+    let mut taken = handles
+        .into_iter()
+        .map(|h| h.join().unwrap().unwrap())
+        .collect::<Vec<_>>();
+    taken.sort();
+    assert_eq!(
+        taken,
+        (0..4)
+            .map(|i| tuple![E::str("job"), E::I(i)])
+            .collect::<Vec<_>>()
+    );
+    assert!(sp.tuple_rd(tuple![E::Any, E::Any]).now_or_never().is_none());
+}
+
+#[test]
+fn test_pending_rd_keeps_tuple() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    let readers = (0..3)
+        .map(|_| sp.tuple_rd(tuple![E::str("foo"), E::Any]))
+        .collect::<Vec<_>>();
+    let taker1 = sp.tuple_in(tuple![E::Any, E::I(42)]);
+    let taker2 = sp.tuple_in(tuple![E::str("foo"), E::Any]);
+
+    // one out wakes every reader but only the oldest taker
+    executor::block_on(sp.tuple_out(tuple![E::str("foo"), E::I(42)])).unwrap();
+    for reader in readers {
+        assert_eq!(
+            executor::block_on(reader).unwrap(),
+            tuple![E::str("foo"), E::I(42)]
+        );
+    }
+    assert_eq!(
+        executor::block_on(taker1).unwrap(),
+        tuple![E::str("foo"), E::I(42)]
+    );
+
+    // a tuple only read by waiters stays in the space
+    let mut taker2 = taker2;
+    assert!((&mut taker2).now_or_never().is_none());
+    let reader = sp.tuple_rd(tuple![E::str("bar")]);
+    executor::block_on(sp.tuple_out(tuple![E::str("bar")])).unwrap();
+    assert_eq!(executor::block_on(reader).unwrap(), tuple![E::str("bar")]);
+    assert_eq!(
+        sp.tuple_in(tuple![E::str("bar")])
+            .now_or_never()
+            .unwrap()
+            .unwrap(),
+        tuple![E::str("bar")]
+    );
+}
+
+#[test]
+fn test_timeout() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    let timed_out =
+        executor::block_on(sp.tuple_in_timeout(tuple![E::Any], Duration::from_millis(10)));
+    assert!(matches!(timed_out, Err(Error::TimedOut)));
+
+    // the timed out waiter does not swallow later tuples
+    executor::block_on(sp.tuple_out(tuple![E::I(1)])).unwrap();
+    assert_eq!(
+        executor::block_on(sp.tuple_rd_timeout(tuple![E::Any], Duration::from_millis(10))).unwrap(),
+        tuple![E::I(1)]
+    );
+}
+
+#[test]
+fn test_timeout_across_threads() {
+    let sp = SharedSpace::new(SimpleStore::new());
+
+    let waiting = sp.tuple_in_timeout(tuple![E::str("task"), E::Any], Duration::from_secs(10));
+    let producer = sp.clone();
+    std::thread::spawn(move || {
+        executor::block_on(producer.tuple_out(tuple![E::str("task"), E::I(3)])).unwrap();
+    });
+    assert_eq!(
+        executor::block_on(waiting).unwrap(),
+        tuple![E::str("task"), E::I(3)]
+    );
+}
+
+#[test]
+fn test_dropped_match_is_removed() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    let waiters = (0..3)
+        .map(|_| sp.tuple_in(tuple![E::Any]))
+        .collect::<Vec<_>>();
+    drop(waiters);
+
+    // none of the abandoned waiters takes the tuple
+    executor::block_on(sp.tuple_out(tuple![E::I(5)])).unwrap();
+    assert_eq!(
+        sp.tuple_in(tuple![E::Any]).now_or_never().unwrap().unwrap(),
+        tuple![E::I(5)]
+    );
+}
+
+#[test]
+fn test_eval() {
+    let sp = SharedSpace::new(SimpleStore::new());
+
+    // the result only shows up once every field has been computed
+    let result = sp.tuple_rd(tuple![E::str("fib"), E::Any]);
+    let evaluation = sp.eval(vec![
+        Field::from(E::str("fib")),
+        Field::compute(|| {
+            let (mut a, mut b) = (0, 1);
+            for _ in 0..10 {
+                (a, b) = (b, a + b);
+            }
+            E::I(a)
+        }),
+    ]);
+    assert_eq!(
+        executor::block_on(result).unwrap(),
+        tuple![E::str("fib"), E::I(55)]
+    );
+    executor::block_on(evaluation).unwrap();
+
+    let evaluation = sp.eval(vec![
+        Field::future(async { E::D(0.5) }),
+        Field::compute(|| E::I(1)),
+    ]);
+    executor::block_on(evaluation).unwrap();
+    assert_eq!(
+        executor::block_on(sp.tuple_in(tuple![E::Any, E::Any])).unwrap(),
+        tuple![E::D(0.5), E::I(1)]
+    );
+}
+
+#[test]
+fn test_eval_failure() {
+    let sp = SharedSpace::new(SimpleStore::new());
+
+    assert!(
+        executor::block_on(sp.eval(vec![Field::compute(|| panic!("field panicked"))])).is_err()
+    );
+    assert!(executor::block_on(sp.eval(vec![Field::compute(|| E::Any)])).is_err());
+    assert!(sp.tuple_rd(tuple![E::Any]).now_or_never().is_none());
+}
+
+#[test]
+fn test_collect() {
+    let mut src = Space::new(SimpleStore::new());
+    let mut dst = Space::new(SimpleStore::new());
+    for i in 0..5 {
+        executor::block_on(src.tuple_out(tuple![E::str("done"), E::I(i)])).unwrap();
+    }
+    executor::block_on(src.tuple_out(tuple![E::str("todo"), E::I(5)])).unwrap();
+
+    // waiters in the destination are served by collected tuples
+    let waiting = dst.tuple_in(tuple![E::str("done"), E::I(4)]);
+    assert_eq!(
+        src.copy_collect(&mut dst, &tuple![E::str("done"), E::Any])
+            .unwrap(),
+        5
+    );
+    assert_eq!(
+        executor::block_on(waiting).unwrap(),
+        tuple![E::str("done"), E::I(4)]
+    );
+    assert_eq!(
+        dst.tuple_rd_all(&tuple![E::str("done"), E::Any])
+            .unwrap()
+            .len(),
+        4
+    );
+
+    assert_eq!(src.collect(&mut dst, &tuple![E::Any, E::Any]).unwrap(), 6);
+    assert!(src
+        .tuple_rd_all(&tuple![E::Any, E::Any])
+        .unwrap()
+        .is_empty());
+    // the copies made before are kept alongside the collected tuples
+    assert_eq!(dst.tuple_in_all(&tuple![E::Any, E::Any]).unwrap().len(), 10);
+}
+
+#[test]
+fn test_shared_collect() {
+    let src = SharedSpace::new(SimpleStore::new());
+    let dst = SharedSpace::new(SimpleStore::new());
+    for i in 0..5 {
+        executor::block_on(src.tuple_out(tuple![E::I(i)])).unwrap();
+    }
+    assert_eq!(src.collect(&dst, &tuple![E::Any]).unwrap(), 5);
+    assert_eq!(dst.copy_collect(&src, &tuple![E::Any]).unwrap(), 5);
+    assert_eq!(
+        src.tuple_in_all(&tuple![E::Any]).unwrap(),
+        dst.tuple_rd_all(&tuple![E::Any]).unwrap()
+    );
+}
+
+#[test]
+fn test_collect_failure() {
+    let mut src = Space::new(SimpleStore::new());
+    let mut dst = Space::new(Bounded(SimpleStore::new(), 1));
+    for i in 0..3 {
+        executor::block_on(src.tuple_out(tuple![E::I(i)])).unwrap();
+    }
+    // the tuples that do not fit into the destination are put back
+    assert!(matches!(
+        src.collect(&mut dst, &tuple![E::Any]),
+        Err(Error::CapacityExceeded)
+    ));
+    assert_eq!(dst.tuple_rd_all(&tuple![E::Any]).unwrap().len(), 1);
+    assert_eq!(src.tuple_rd_all(&tuple![E::Any]).unwrap().len(), 2);
+
+    let src = SharedSpace::new(SimpleStore::new());
+    let dst = SharedSpace::new(Bounded(SimpleStore::new(), 1));
+    for i in 0..3 {
+        executor::block_on(src.tuple_out(tuple![E::I(i)])).unwrap();
+    }
+    assert!(matches!(
+        src.collect(&dst, &tuple![E::Any]),
+        Err(Error::CapacityExceeded)
+    ));
+    assert_eq!(dst.tuple_rd_all(&tuple![E::Any]).unwrap().len(), 1);
+    assert_eq!(src.tuple_rd_all(&tuple![E::Any]).unwrap().len(), 2);
+}
+
+#[test]
+fn test_try_in_rd() {
+    let mut sp = Space::new(SimpleStore::new());
+
+    // misses return right away and leave no waiter behind
+    assert_eq!(sp.try_in(&tuple![E::str("foo"), E::Any]).unwrap(), None);
+    assert_eq!(sp.try_rd(&tuple![E::str("foo"), E::Any]).unwrap(), None);
+    executor::block_on(sp.tuple_out(tuple![E::str("foo"), E::I(1)])).unwrap();
+
+    assert_eq!(
+        sp.try_rd(&tuple![E::str("foo"), E::Any]).unwrap(),
+        Some(tuple![E::str("foo"), E::I(1)])
+    );
+    assert_eq!(
+        sp.try_in(&tuple![E::str("foo"), E::Any]).unwrap(),
+        Some(tuple![E::str("foo"), E::I(1)])
+    );
+    assert_eq!(sp.try_in(&tuple![E::str("foo"), E::Any]).unwrap(), None);
+}
+
+#[test]
+fn test_duplicate_tokens() {
+    let mut sp = Space::new(SimpleStore::new());
+    for _ in 0..10 {
+        executor::block_on(sp.tuple_out(tuple![E::str("token")])).unwrap();
+    }
+    for _ in 0..10 {
+        assert_eq!(
+            executor::block_on(sp.tuple_in(tuple![E::str("token")])).unwrap(),
+            tuple![E::str("token")]
+        );
+    }
+    assert!(sp
+        .tuple_in(tuple![E::str("token")])
+        .now_or_never()
+        .is_none());
+}
+
+#[test]
+fn test_job_queue() {
+    // a fifo store hands out the oldest job first, whatever its name
+    let mut sp = Space::new(SimpleStore::with_policy(Policy::Fifo));
+    assert_eq!(sp.policy(), Policy::Fifo);
+    for job in ["zip", "archive", "mail"] {
+        executor::block_on(sp.tuple_out(tuple![E::str("job"), E::str(job)])).unwrap();
+    }
+    assert_eq!(
+        executor::block_on(sp.tuple_in(tuple![E::str("job"), E::Any])).unwrap(),
+        tuple![E::str("job"), E::str("zip")]
+    );
+    assert_eq!(
+        sp.tuple_in_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![
+            tuple![E::str("job"), E::str("archive")],
+            tuple![E::str("job"), E::str("mail")]
+        ]
+    );
+}
+
+#[test]
+fn test_subscribe() {
+    let mut sp = Space::new(SimpleStore::new());
+    let mut sub = sp.subscribe(tuple![E::str("foo"), E::Any]).unwrap();
+    let taker = sp.tuple_in(tuple![E::Any, E::I(1)]);
+
+    // subscribers see every matching tuple, even one consumed by a pending in
+    executor::block_on(sp.tuple_out(tuple![E::str("foo"), E::I(1)])).unwrap();
+    executor::block_on(sp.tuple_out(tuple![E::str("foo"), E::I(2)])).unwrap();
+    executor::block_on(sp.tuple_out(tuple![E::str("bar"), E::I(3)])).unwrap();
+    assert_eq!(
+        executor::block_on(taker).unwrap(),
+        tuple![E::str("foo"), E::I(1)]
+    );
+    assert_eq!(
+        executor::block_on(sub.next()),
+        Some(tuple![E::str("foo"), E::I(1)])
+    );
+    assert_eq!(
+        executor::block_on(sub.next()),
+        Some(tuple![E::str("foo"), E::I(2)])
+    );
+    assert!(sub.next().now_or_never().is_none());
+
+    // the tuples are not consumed by the subscription
+    assert_eq!(
+        sp.try_in(&tuple![E::str("foo"), E::Any]).unwrap(),
+        Some(tuple![E::str("foo"), E::I(2)])
+    );
+
+    // a dropped subscription ends the stream and is removed from the space
+    drop(sub);
+    let mut sub = sp.subscribe(tuple![E::Any, E::Any]).unwrap();
+    drop(sp);
+    assert_eq!(executor::block_on(sub.next()), None);
+}
+
+#[test]
+fn test_shared_subscribe() {
+    let sp = SharedSpace::new(SimpleStore::new());
+    let sub = sp.subscribe(tuple![E::Any]).unwrap();
+    let producer = sp.clone();
+    let handle = std::thread::spawn(move || {
+        for i in 0..5 {
+            executor::block_on(producer.tuple_out(tuple![E::I(i)])).unwrap();
+        }
+    });
+    let seen = executor::block_on(sub.take(5).collect::<Vec<_>>());
+    handle.join().unwrap();
+    assert_eq!(seen, (0..5).map(|i| tuple![E::I(i)]).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_ttl() {
+    let mut sp = Space::new(SimpleStore::new());
+    // a pending in consumes the tuple right away, so there is nothing to lease
+    let taker = sp.tuple_in(tuple![E::Any]);
+    assert_eq!(
+        sp.tuple_out_with_ttl(tuple![E::I(1)], Duration::from_secs(60))
+            .unwrap(),
+        None
+    );
+    assert_eq!(executor::block_on(taker).unwrap(), tuple![E::I(1)]);
+
+    let lease = sp
+        .tuple_out_with_ttl(tuple![E::I(2)], Duration::from_millis(50))
+        .unwrap()
+        .unwrap();
+    assert_eq!(sp.try_rd(&tuple![E::Any]).unwrap(), Some(tuple![E::I(2)]));
+    assert!(sp.renew(&lease, Duration::from_millis(50)).unwrap());
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(sp.try_rd(&tuple![E::Any]).unwrap(), None);
+    assert!(!sp.cancel(&lease).unwrap());
+    assert_eq!(sp.purge_expired().unwrap(), 1);
+}
+
+#[test]
+fn test_purge_expired_in_background() {
+    let sp = SharedSpace::new(SimpleStore::new());
+    sp.purge_expired_every(Duration::from_millis(10));
+    sp.tuple_out_with_ttl(tuple![E::I(1)], Duration::from_millis(20))
+        .unwrap();
+    executor::block_on(sp.tuple_out(tuple![E::I(2)])).unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    // the reaper got to the expired tuple first
+    assert_eq!(sp.purge_expired().unwrap(), 0);
+    assert_eq!(
+        sp.tuple_rd_all(&tuple![E::Any]).unwrap(),
+        vec![tuple![E::I(2)]]
+    );
+}
+
+#[test]
+fn test_transaction() {
+    let mut sp = Space::new(SimpleStore::new());
+    executor::block_on(sp.tuple_out(tuple![E::str("task"), E::I(1), E::str("pending")])).unwrap();
+    let running = sp.tuple_rd(tuple![E::str("task"), E::I(1), E::str("running")]);
+
+    // aborted transactions restore taken tuples and discard their outs
+    let mut txn = sp.transaction();
+    assert!(txn
+        .try_in(&tuple![E::str("task"), E::Any, E::str("pending")])
+        .unwrap()
+        .is_some());
+    txn.tuple_out(tuple![E::str("task"), E::I(1), E::str("running")])
+        .unwrap();
+    assert!(txn.tuple_out(tuple![E::Any]).is_err());
+    // the transaction sees its own outs
+    assert!(txn
+        .try_rd(&tuple![E::str("task"), E::I(1), E::str("running")])
+        .unwrap()
+        .is_some());
+    txn.abort();
+    assert!(sp
+        .try_rd(&tuple![E::str("task"), E::I(1), E::str("pending")])
+        .unwrap()
+        .is_some());
+    assert!(sp
+        .try_rd(&tuple![E::str("task"), E::I(1), E::str("running")])
+        .unwrap()
+        .is_none());
+
+    // dropping a transaction aborts it too
+    let mut txn = sp.transaction();
+    assert!(txn
+        .try_in(&tuple![E::str("task"), E::Any, E::Any])
+        .unwrap()
+        .is_some());
+    drop(txn);
+    assert_eq!(
+        sp.tuple_rd_all(&tuple![E::Any, E::Any, E::Any])
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // committed outs are handed over to pending matches
+    let mut txn = sp.transaction();
+    txn.try_in(&tuple![E::str("task"), E::I(1), E::str("pending")])
+        .unwrap();
+    txn.tuple_out(tuple![E::str("task"), E::I(1), E::str("running")])
+        .unwrap();
+    txn.commit().unwrap();
+    assert_eq!(
+        executor::block_on(running).unwrap(),
+        tuple![E::str("task"), E::I(1), E::str("running")]
+    );
+    assert_eq!(
+        sp.tuple_rd_all(&tuple![E::Any, E::Any, E::Any]).unwrap(),
+        vec![tuple![E::str("task"), E::I(1), E::str("running")]]
+    );
+}
+
+#[test]
+fn test_transaction_abort() {
+    let mut sp = Space::new(SimpleStore::with_policy(Policy::Fifo));
+    let mut sub = sp.subscribe(tuple![E::str("job"), E::Any]).unwrap();
+    for i in 1..=3 {
+        executor::block_on(sp.tuple_out(tuple![E::str("job"), E::I(i)])).unwrap();
+    }
+    executor::block_on(sp.tuple_out(tuple![E::str("job"), E::I(1)])).unwrap();
+    let lease = sp
+        .tuple_out_with_ttl(tuple![E::str("lease")], Duration::from_millis(50))
+        .unwrap()
+        .unwrap();
+
+    let mut txn = sp.transaction();
+    assert_eq!(
+        txn.try_in(&tuple![E::str("job"), E::I(1)]).unwrap(),
+        Some(tuple![E::str("job"), E::I(1)])
+    );
+    // the second copy is still there for the transaction, but not a third one
+    assert_eq!(
+        txn.try_in(&tuple![E::str("job"), E::I(1)]).unwrap(),
+        Some(tuple![E::str("job"), E::I(1)])
+    );
+    assert_eq!(txn.try_rd(&tuple![E::str("job"), E::I(1)]).unwrap(), None);
+    assert!(txn.try_in(&tuple![E::str("lease")]).unwrap().is_some());
+    assert_eq!(txn.try_rd(&tuple![E::str("lease")]).unwrap(), None);
+    txn.abort();
+
+    // aborted tuples keep their place in the order, their lease and are not announced again
+    assert_eq!(
+        sp.try_in(&tuple![E::str("job"), E::Any]).unwrap(),
+        Some(tuple![E::str("job"), E::I(1)])
+    );
+    for _ in 0..4 {
+        assert!(sub.next().now_or_never().flatten().is_some());
+    }
+    assert!(sub.next().now_or_never().is_none());
+    assert!(sp.renew(&lease, Duration::from_millis(50)).unwrap());
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(sp.try_rd(&tuple![E::str("lease")]).unwrap(), None);
+
+    // committing removes exactly the copies that were taken
+    let mut txn = sp.transaction();
+    assert_eq!(
+        txn.try_in(&tuple![E::str("job"), E::Any]).unwrap(),
+        Some(tuple![E::str("job"), E::I(2)])
+    );
+    txn.commit().unwrap();
+    assert_eq!(
+        sp.tuple_rd_all(&tuple![E::str("job"), E::Any]).unwrap(),
+        vec![
+            tuple![E::str("job"), E::I(3)],
+            tuple![E::str("job"), E::I(1)]
+        ]
+    );
+
+    // a taken NaN is hidden from the transaction like any other tuple
+    executor::block_on(sp.tuple_out(tuple![E::D(f64::NAN)])).unwrap();
+    let mut txn = sp.transaction();
+    assert!(txn
+        .try_in(&tuple![E::AnyOf(Type::Float)])
+        .unwrap()
+        .is_some());
+    assert!(txn
+        .try_rd(&tuple![E::AnyOf(Type::Float)])
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_shared_transaction() {
+    let sp = SharedSpace::new(SimpleStore::new());
+    executor::block_on(sp.tuple_out(tuple![E::str("counter"), E::I(0)])).unwrap();
+
+    let handles = (0..4)
+        .map(|_| {
+            let sp = sp.clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    sp.transaction(|txn| -> Result<(), Box<dyn std::error::Error>> {
+                        let Some(counter) = txn.try_in(&tuple![E::str("counter"), E::Any])? else {
+                            return Err("counter is missing".into());
+                        };
+                        let E::I(n) = counter.rest().first().clone() else {
+                            return Err("counter is not an int".into());
+                        };
+                        Ok(txn.tuple_out(tuple![E::str("counter"), E::I(n + 1)])?)
+                    })
+                    .unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(
+        sp.try_rd(&tuple![E::str("counter"), E::Any]).unwrap(),
+        Some(tuple![E::str("counter"), E::I(100)])
+    );
+
+    // a failing transaction leaves the space untouched
+    let result: Result<(), Box<dyn std::error::Error>> = sp.transaction(|txn| {
+        txn.try_in(&tuple![E::str("counter"), E::Any])?;
+        Err("giving up".into())
+    });
+    assert!(result.is_err());
+    assert_eq!(
+        sp.try_rd(&tuple![E::str("counter"), E::Any]).unwrap(),
+        Some(tuple![E::str("counter"), E::I(100)])
+    );
+}
+
+#[test]
+fn test_transaction_arity() {
+    let mut sp = Space::new(SimpleStore::new());
+    let mut txn = sp.transaction();
+    txn.tuple_out(tuple![E::I(1), E::I(2), E::I(3)]).unwrap();
+    // buffered tuples only match templates of their arity
+    assert_eq!(txn.try_rd(&tuple![E::I(1), E::Any]).unwrap(), None);
+    assert_eq!(txn.try_in(&tuple![E::I(1), E::Any]).unwrap(), None);
+    assert_eq!(
+        txn.try_in(&tuple![E::I(1), E::Any, E::Any]).unwrap(),
+        Some(tuple![E::I(1), E::I(2), E::I(3)])
+    );
+}
+
+#[test]
+fn test_transaction_commit_failure() {
+    let mut sp = Space::new(Bounded(SimpleStore::new(), 2));
+    executor::block_on(sp.tuple_out(tuple![E::str("a")])).unwrap();
+    executor::block_on(sp.tuple_out(tuple![E::str("b")])).unwrap();
+    let waiting = sp.tuple_rd(tuple![E::str("c")]);
+
+    let mut txn = sp.transaction();
+    assert!(txn.try_in(&tuple![E::str("a")]).unwrap().is_some());
+    for name in ["c", "d"] {
+        txn.tuple_out(tuple![E::str(name)]).unwrap();
+    }
+    assert!(matches!(txn.commit(), Err(Error::CapacityExceeded)));
+
+    // the failed commit changed nothing and served no one
+    assert_eq!(
+        sp.tuple_rd_all(&tuple![E::Any]).unwrap(),
+        vec![tuple![E::str("a")], tuple![E::str("b")]]
+    );
+    let mut waiting = waiting;
+    assert!((&mut waiting).now_or_never().is_none());
+}
+
+/// A store that holds at most the given number of tuples.
+struct Bounded(SimpleStore, usize);
+
+impl Store for Bounded {
+    fn inp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.0.inp(tup)
+    }
+
+    fn rdp(&mut self, tup: &Tuple) -> Result<Option<Tuple>, Error> {
+        self.0.rdp(tup)
+    }
+
+    fn in_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.0.in_all(tup)
+    }
+
+    fn rd_all(&mut self, tup: &Tuple) -> Result<Vec<Tuple>, Error> {
+        self.0.rd_all(tup)
+    }
+
+    fn out(&mut self, tup: Tuple) -> Result<(), Error> {
+        if self.0.len() >= self.1 {
+            return Err(Error::CapacityExceeded);
+        }
+        self.0.out(tup)
+    }
+
+    fn out_with_ttl(&mut self, tup: Tuple, ttl: Duration) -> Result<Lease, Error> {
+        if self.0.len() >= self.1 {
+            return Err(Error::CapacityExceeded);
+        }
+        self.0.out_with_ttl(tup, ttl)
+    }
+
+    fn renew(&mut self, lease: &Lease, ttl: Duration) -> Result<bool, Error> {
+        self.0.renew(lease, ttl)
+    }
+
+    fn cancel(&mut self, lease: &Lease) -> Result<bool, Error> {
+        self.0.cancel(lease)
+    }
+
+    fn purge_expired(&mut self) -> Result<usize, Error> {
+        self.0.purge_expired()
+    }
+
+    fn policy(&self) -> Policy {
+        self.0.policy()
+    }
+}
@@ -1,9 +1,12 @@
 use std::cmp::Ordering;
 use std::collections::Bound;
+use std::ops::RangeBounds;
 
 #[macro_use]
 extern crate rustupolis;
-use rustupolis::tuple::E;
+use regex::Regex;
+use rustupolis::constraint::Constraint;
+use rustupolis::tuple::{Type, E};
 
 #[test]
 fn test_emptiness() {
@@ -17,6 +20,10 @@ fn test_emptiness() {
     assert!(E::T(tuple![]).matches(&E::Any));
     assert!(E::Any.matches(&E::T(tuple![])));
     assert!(!E::T(tuple![]).matches(&E::T(tuple![E::I(42)])));
+    // only tuples of the same arity match
+    assert!(!tuple![E::I(1), E::Any].matches(&tuple![E::I(1), E::I(2), E::I(3)]));
+    assert!(!tuple![E::I(1), E::I(2), E::I(3)].matches(&tuple![E::I(1), E::Any]));
+    assert!(!E::T(tuple![E::I(1)]).matches(&E::T(tuple![E::I(1), E::I(2)])));
 }
 
 #[test]
@@ -125,3 +132,227 @@ fn nested_compare_with_empty() {
         ])
     );
 }
+
+#[test]
+fn test_wide_types() {
+    let values = [
+        E::I(-1),
+        E::I(1),
+        E::L(-1),
+        E::L(i64::MAX),
+        E::U(0),
+        E::U(u64::MAX),
+        E::D(0.5),
+        E::B(false),
+        E::B(true),
+        E::str("foo"),
+        E::R(vec![]),
+        E::R(vec![0, 255]),
+        E::T(tuple![]),
+    ];
+    // Values are ordered by type first, then by value.
+    for (i, a) in values.iter().enumerate() {
+        assert!(a.is_defined());
+        assert!(E::Any < *a && *a < E::None);
+        for (j, b) in values.iter().enumerate() {
+            assert_eq!(a.cmp(b), i.cmp(&j), "{a:?} <=> {b:?}");
+            assert_eq!(a.matches(b), i == j, "{a:?} matches {b:?}");
+        }
+        assert!(a.matches(&E::Any));
+    }
+    // Equal numbers of different types are different values.
+    assert!(!E::I(1).matches(&E::L(1)));
+    assert!(!E::L(1).matches(&E::U(1)));
+
+    assert_eq!(E::L(42).to_string(), "42i64");
+    assert_eq!(E::U(42).to_string(), "42u64");
+    assert_eq!(E::B(true).to_string(), "true");
+    assert_eq!(
+        E::str("say \"hi\"\n\u{7}").to_string(),
+        "\"say \\\"hi\\\"\\n\\u{7}\""
+    );
+    assert_eq!(
+        E::R(b"a\"\\\x00".to_vec()).to_string(),
+        "b\"a\\\"\\\\\\x00\""
+    );
+}
+
+#[test]
+fn test_typed_wildcards() {
+    let values = [
+        (E::I(0), Type::Int),
+        (E::L(0), Type::Long),
+        (E::U(0), Type::Unsigned),
+        (E::D(0.0), Type::Float),
+        (E::B(false), Type::Bool),
+        (E::str(""), Type::Str),
+        (E::R(vec![]), Type::Bytes),
+        (E::T(tuple![]), Type::Tuple),
+    ];
+    for (i, (a, a_type)) in values.iter().enumerate() {
+        assert_eq!(a.type_of(), Some(*a_type));
+        let formal = E::AnyOf(*a_type);
+        assert!(!formal.is_defined());
+        // A typed wildcard sorts right before all values of its type.
+        assert!(E::Any < formal && formal < E::None);
+        for (j, (b, _)) in values.iter().enumerate() {
+            assert_eq!(formal.matches(b), i == j, "{formal:?} matches {b:?}");
+            assert_eq!(b.matches(&formal), i == j, "{b:?} matches {formal:?}");
+            assert_eq!(formal < *b, i <= j, "{formal:?} < {b:?}");
+        }
+    }
+    // Formals do not match formals.
+    assert!(!E::AnyOf(Type::Int).matches(&E::AnyOf(Type::Int)));
+    assert!(!E::AnyOf(Type::Int).matches(&E::Any));
+    assert!(!E::Any.matches(&E::AnyOf(Type::Int)));
+
+    // The range of a typed wildcard ends where the next type begins.
+    assert_eq!(
+        tuple![E::str("foo"), E::AnyOf(Type::Int)].range(),
+        (
+            Bound::Excluded(tuple![E::str("foo"), E::AnyOf(Type::Int)]),
+            Bound::Excluded(tuple![E::str("foo"), E::AnyOf(Type::Long)]),
+        )
+    );
+    assert_eq!(
+        tuple![E::AnyOf(Type::Tuple)].range(),
+        (
+            Bound::Excluded(tuple![E::AnyOf(Type::Tuple)]),
+            Bound::Excluded(tuple![E::None]),
+        )
+    );
+}
+
+fn constraint(c: Constraint) -> E {
+    E::Where(Box::new(c))
+}
+
+#[test]
+fn test_constraints() {
+    let at_least_5 = constraint(Constraint::Range(
+        Bound::Included(E::I(5)),
+        Bound::Unbounded,
+    ));
+    let from_1_to_3 = constraint(Constraint::Range(
+        Bound::Excluded(E::I(1)),
+        Bound::Included(E::I(3)),
+    ));
+    let prefix = constraint(Constraint::Prefix("ta".to_string()));
+    let regex = constraint(Constraint::Regex(Regex::new("^t.+k$").unwrap()));
+    let one_of = constraint(Constraint::OneOf(vec![E::str("b"), E::I(2)]));
+
+    assert!(!at_least_5.is_defined());
+    assert!(at_least_5.matches(&E::I(5)) && E::I(7).matches(&at_least_5));
+    assert!(!at_least_5.matches(&E::I(4)));
+    // Ranges only match values of the same type as their bounds.
+    assert!(!at_least_5.matches(&E::L(7)) && !at_least_5.matches(&E::D(7.0)));
+    assert!(!from_1_to_3.matches(&E::I(1)));
+    assert!(from_1_to_3.matches(&E::I(2)) && from_1_to_3.matches(&E::I(3)));
+    assert!(!from_1_to_3.matches(&E::I(4)));
+    assert!(prefix.matches(&E::str("task")) && !prefix.matches(&E::str("job")));
+    assert!(regex.matches(&E::str("task")) && !regex.matches(&E::str("tasks")));
+    assert!(one_of.matches(&E::I(2)) && one_of.matches(&E::str("b")));
+    assert!(!one_of.matches(&E::I(3)) && !one_of.matches(&E::L(2)));
+    // Formals do not match formals.
+    for c in [&at_least_5, &prefix, &one_of] {
+        assert!(!c.matches(&E::Any) && !c.matches(&E::AnyOf(Type::Int)) && !c.matches(c));
+    }
+
+    // Constraints sort right before or after the bound of their range.
+    assert!(E::I(4) < at_least_5 && at_least_5 < E::I(5));
+    assert!(E::I(1) < from_1_to_3 && from_1_to_3 < E::I(2));
+    assert!(E::str("t") < prefix && prefix < E::str("ta"));
+    assert!(E::AnyOf(Type::Str) < regex && regex < E::str(""));
+
+    // All matching tuples lie within the range of the template.
+    for (template, inside, outside) in [
+        (&at_least_5, E::I(i32::MAX), E::L(0)),
+        (&from_1_to_3, E::I(3), E::I(4)),
+        (&prefix, E::str("ta\u{10ffff}"), E::str("tb")),
+        (&regex, E::str("\u{10ffff}"), E::R(vec![])),
+        (&one_of, E::str("b"), E::str("c")),
+    ] {
+        let tup = tuple![template.clone(), E::Any];
+        let range = (tup.range().0, tup.range().1);
+        assert!(range.contains(&tuple![inside, E::I(0)]), "{template}");
+        assert!(!range.contains(&tuple![outside, E::I(0)]), "{template}");
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let tup = tuple![
+        E::I(-1),
+        E::L(i64::MIN),
+        E::U(u64::MAX),
+        E::D(0.5),
+        E::B(true),
+        E::str("foo"),
+        E::R(vec![0, 255]),
+        E::T(tuple![E::I(1), E::T(tuple![])]),
+        E::Any,
+        E::AnyOf(Type::Float),
+        constraint(Constraint::Range(
+            Bound::Excluded(E::I(1)),
+            Bound::Unbounded
+        )),
+        constraint(Constraint::Prefix("ta".to_string())),
+        constraint(Constraint::Regex(Regex::new("^t.+k$").unwrap())),
+        constraint(Constraint::OneOf(vec![E::str("a"), E::I(2)])),
+        E::None
+    ];
+    let json = serde_json::to_string(&tup).unwrap();
+    assert_eq!(
+        serde_json::from_str::<rustupolis::Tuple>(&json).unwrap(),
+        tup
+    );
+
+    // Values are tagged with their type, wildcards are plain strings.
+    assert_eq!(
+        serde_json::to_string(&tuple![E::I(42), E::str("foo"), E::Any, E::None]).unwrap(),
+        r#"[{"int":42},{"str":"foo"},"any","none"]"#
+    );
+    assert_eq!(
+        serde_json::from_str::<rustupolis::Tuple>(r#"[{"any_of":"tuple"},{"tuple":[]}]"#).unwrap(),
+        tuple![E::AnyOf(Type::Tuple), E::T(tuple![])]
+    );
+    assert!(serde_json::from_str::<rustupolis::Tuple>(r#"[{"where":{"regex":"("}}]"#).is_err());
+}
+
+#[test]
+fn test_access() {
+    let mut tup = tuple![E::str("foo"), E::I(1)];
+    assert_eq!(tup.len(), 2);
+    assert_eq!(tup.arity(), 2);
+    assert_eq!(tup.get(0), Some(&E::str("foo")));
+    assert_eq!(tup.get(2), None);
+    assert_eq!(tup[1], E::I(1));
+    assert_eq!(tuple![].get(0), None);
+
+    tup.push(E::T(tuple![E::B(true)]));
+    assert_eq!(tup.arity(), 3);
+    assert_eq!(&tup.as_slice()[1..], &[E::I(1), E::T(tuple![E::B(true)])]);
+    assert_eq!(
+        tup.iter().filter_map(E::type_of).collect::<Vec<_>>(),
+        vec![Type::Str, Type::Int, Type::Tuple]
+    );
+    let mut count = 0;
+    for e in &tup {
+        assert!(e.is_defined());
+        count += 1;
+    }
+    assert_eq!(count, 3);
+
+    let doubled = tup
+        .into_iter()
+        .map(|e| match e {
+            E::I(i) => E::I(i * 2),
+            e => e,
+        })
+        .collect::<rustupolis::Tuple>();
+    assert_eq!(
+        doubled,
+        tuple![E::str("foo"), E::I(2), E::T(tuple![E::B(true)])]
+    );
+}
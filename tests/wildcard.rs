@@ -63,3 +63,82 @@ fn nested_matching() {
         Some("doit".to_string())
     );
 }
+
+#[test]
+fn queued_in_order() {
+    let mut t = Tree::new();
+    t.insert(tuple![E::Any], 1).unwrap();
+    t.insert(tuple![E::I(42)], 2).unwrap();
+    t.insert(tuple![E::Any], 3).unwrap();
+    // Oldest first, regardless of the path
+    assert_eq!(t.take(tuple![E::I(42)]), Some(1));
+    assert_eq!(t.take(tuple![E::I(42)]), Some(2));
+    assert_eq!(t.take(tuple![E::I(42)]), Some(3));
+    assert!(t.is_empty());
+}
+
+#[test]
+fn take_selected() {
+    let mut t = Tree::new();
+    for i in 0..6 {
+        t.insert(tuple![E::str("foo"), E::Any], i).unwrap();
+    }
+    t.insert(tuple![E::Any, E::I(0)], 6).unwrap();
+    t.insert(tuple![E::Any, E::I(1)], 7).unwrap();
+
+    assert_eq!(
+        t.take_first_if(&tuple![E::str("foo"), E::I(1)], |i| i % 2 == 1),
+        Some(1)
+    );
+    assert_eq!(
+        t.take_all_if(&tuple![E::str("foo"), E::I(1)], |i| i % 2 == 1),
+        vec![3, 5, 7]
+    );
+    assert_eq!(
+        t.take_all_if(&tuple![E::str("bar"), E::I(0)], |_| true),
+        vec![6]
+    );
+    assert_eq!(
+        t.take_all_if(&tuple![E::str("foo"), E::I(0)], |_| true),
+        vec![0, 2, 4]
+    );
+    assert!(t.is_empty());
+}
+
+#[test]
+fn remove_by_key() {
+    let mut t = Tree::new();
+    let first = t.insert(tuple![E::str("foo"), E::Any], 1).unwrap();
+    let second = t.insert(tuple![E::str("foo"), E::Any], 2).unwrap();
+    // Only the exact path leads to the item
+    assert_eq!(t.remove(&tuple![E::str("foo"), E::I(0)], first), None);
+    assert_eq!(t.remove(&tuple![E::str("foo"), E::Any], first), Some(1));
+    assert_eq!(t.remove(&tuple![E::str("foo"), E::Any], first), None);
+    assert_eq!(t.remove(&tuple![E::str("foo"), E::Any], second), Some(2));
+    assert!(t.is_empty());
+}
+
+#[test]
+fn remove_nan() {
+    let mut t = Tree::new();
+    let first = t.insert(tuple![E::D(f64::NAN)], 1).unwrap();
+    let second = t.insert(tuple![E::D(f64::NAN)], 2).unwrap();
+    // A NaN leads to the path it was inserted along, and to a single one
+    assert_eq!(t.remove(&tuple![E::D(f64::NAN)], first), Some(1));
+    assert!(!t.is_empty());
+    assert_eq!(t.remove(&tuple![E::D(f64::NAN)], second), Some(2));
+    assert!(t.is_empty());
+}
+
+#[test]
+fn visit_matching() {
+    let mut t = Tree::new();
+    t.insert(tuple![E::str("foo"), E::Any], 1).unwrap();
+    t.insert(tuple![E::str("bar"), E::Any], 2).unwrap();
+    t.insert(tuple![E::Any, E::I(1)], 3).unwrap();
+    let mut visited = Vec::new();
+    t.visit_matching(&tuple![E::str("foo"), E::I(1)], |i| visited.push(*i));
+    assert_eq!(visited, vec![1, 3]);
+    // Visiting leaves the items in the tree
+    assert_eq!(t.take(tuple![E::str("foo"), E::I(1)]), Some(1));
+}